@@ -0,0 +1,638 @@
+//! Headless interface, enabled by the `console` feature.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+
+use crate::detect::{background::{self, BackgroundParams, Estimator}, batch, cascades::Library, doctor, history, line::{self, CountingLine}, roi, track::{self, TrackerParams}, video, Backend, Detector, DiffConnect, DiffParams, HaarCascade, HaarParams, Input, Region};
+
+/// Count cars in images. Opens the desktop app when no command is given.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Implementation the methods run on
+    #[arg(long, global = true, default_value_t = Backend::default(), value_parser = parse_backend)]
+    pub backend: Backend,
+    /// JSON file with the polygons the methods are restricted to, the cars of each include
+    /// region are also counted apart
+    #[arg(long, global = true)]
+    pub roi: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Count the cars in an image with the Haar Cascade method
+    Haar {
+        image: PathBuf,
+        /// Where to write the annotated image, its extension selects the format
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        haar: HaarArgs,
+    },
+    /// Count the cars that changed between two images with the Diff & Connect method
+    Diff {
+        before: PathBuf,
+        after: PathBuf,
+        /// Where to write the annotated image, its extension selects the format
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+    /// Count the cars in every image of a directory or glob pattern
+    Batch {
+        #[command(subcommand)]
+        method: BatchMethod,
+    },
+    /// Estimate the empty scene from many frames, and count the cars of an image against it
+    Background {
+        /// Directory with the frames or a glob pattern like `frames/*.png`
+        source: PathBuf,
+        /// Image compared with the background by the Diff & Connect method
+        #[arg(long)]
+        image: Option<PathBuf>,
+        /// Where to write the background as PNG, defaults to the data directory
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// How the background is estimated
+        #[arg(long, default_value = "median", value_parser = parse_estimator)]
+        estimator: Estimator,
+        /// Weight of each new frame in the running average, from 0 to 1
+        #[arg(long, default_value_t = BackgroundParams::default().rate)]
+        rate: f64,
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+    /// Count the cars in every frame of a video and write it annotated
+    Video {
+        #[command(subcommand)]
+        method: VideoMethod,
+    },
+    /// Manage the cascade models available to the Haar Cascade method
+    Cascades {
+        #[command(subcommand)]
+        command: CascadesCommand,
+    },
+    /// List the past runs of the haar and diff commands and of the desktop app, newest first
+    History,
+    /// Check that Python, OpenCV and the data directory are ready
+    Doctor,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CascadesCommand {
+    /// List the installed cascades, the last one used is marked with `*`
+    List,
+    /// Install an OpenCV cascade XML
+    Add {
+        xml: PathBuf,
+        /// Name of the cascade, defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+        /// What the cascade detects
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// Remove an installed cascade
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BatchMethod {
+    /// Count the cars in each image with the Haar Cascade method
+    Haar {
+        #[command(flatten)]
+        batch: BatchArgs,
+        #[command(flatten)]
+        haar: HaarArgs,
+    },
+    /// Compare each image with the next one, sorted by name, with the Diff & Connect method
+    Diff {
+        #[command(flatten)]
+        batch: BatchArgs,
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VideoMethod {
+    /// Count the cars in each frame with the Haar Cascade method
+    Haar {
+        #[command(flatten)]
+        video: VideoArgs,
+        #[command(flatten)]
+        haar: HaarArgs,
+    },
+    /// Compare each frame with a background image with the Diff & Connect method
+    Diff {
+        #[command(flatten)]
+        video: VideoArgs,
+        /// Image of the empty scene, defaults to the first frame of the video
+        #[arg(long)]
+        reference: Option<PathBuf>,
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct VideoArgs {
+    /// Video to read, usually an MP4 or AVI file
+    video: PathBuf,
+    /// Where to write the annotated video, as MP4 or AVI, defaults to the data directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Count the tracked cars crossing the line from X1,Y1 to X2,Y2, in pixels of the frames
+    ///
+    /// Cars crossing to the right of the line, seen going from its start to its end, count as in.
+    #[arg(long, value_parser = parse_line)]
+    line: Option<CountingLine>,
+    /// Length of the spans the crossings are grouped in, in seconds
+    #[arg(long, default_value_t = 60.)]
+    bucket: f64,
+    #[command(flatten)]
+    track: TrackArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Directory with the images or a glob pattern like `frames/*.png`
+    source: PathBuf,
+    /// Directory where the annotated images are written, defaults to a new one in the data directory
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+    #[command(flatten)]
+    track: TrackArgs,
+}
+
+/// Tracker parameters, only used with `--track` or `--line`.
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Tracking")]
+pub struct TrackArgs {
+    /// Follow the cars from one frame to the next and count each car once
+    #[arg(long)]
+    track: bool,
+    /// Smallest overlap of two boxes of the same car, from 0 to 1
+    #[arg(long, default_value_t = TrackerParams::default().min_iou)]
+    min_iou: f32,
+    /// Farthest a car moves between frames, relative to the diagonal of its box
+    #[arg(long, default_value_t = TrackerParams::default().max_distance)]
+    max_distance: f32,
+    /// Frames a car can go undetected before its track ends
+    #[arg(long, default_value_t = TrackerParams::default().max_missed)]
+    max_missed: usize,
+    /// Detections a track needs to count as a car
+    #[arg(long, default_value_t = TrackerParams::default().min_hits)]
+    min_hits: usize,
+}
+
+impl TrackArgs {
+    fn tracker(&self) -> TrackerParams {
+        TrackerParams {
+            min_iou: self.min_iou,
+            max_distance: self.max_distance,
+            max_missed: self.max_missed,
+            min_hits: self.min_hits,
+        }
+    }
+
+    /// The parameters of the tracker, `None` without `--track`.
+    fn params(&self) -> Option<TrackerParams> {
+        self.track.then(|| self.tracker())
+    }
+}
+
+/// Haar Cascade parameters, each flag overrides the value of the preset.
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Haar Cascade")]
+pub struct HaarArgs {
+    /// Name of an installed cascade, else path to a cascade XML, defaults to the last one used
+    #[arg(long)]
+    cascade: Option<String>,
+    /// Starting values for the parameters
+    #[arg(long, default_value = "default", value_parser = clap::builder::PossibleValuesParser::new(HaarParams::PRESETS))]
+    preset: String,
+    /// How much the image shrinks between each scale
+    #[arg(long)]
+    scale_factor: Option<f64>,
+    /// Overlapping windows needed to keep a detection
+    #[arg(long)]
+    min_neighbors: Option<i32>,
+    /// Smallest object to look for, as WIDTHxHEIGHT
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<(i32, i32)>,
+    /// Biggest object to look for, as WIDTHxHEIGHT
+    #[arg(long, value_parser = parse_size)]
+    max_size: Option<(i32, i32)>,
+    /// Size of the Gaussian blur kernel, must be odd
+    #[arg(long)]
+    blur: Option<i32>,
+    /// Size of the dilation kernel
+    #[arg(long)]
+    dilate: Option<i32>,
+    /// Size of the closing kernel
+    #[arg(long)]
+    close: Option<i32>,
+}
+
+impl HaarArgs {
+    fn params(&self) -> HaarParams {
+        let mut params = HaarParams::preset(&self.preset).unwrap_or_default();
+        if let Some(scale_factor) = self.scale_factor { params.scale_factor = scale_factor; }
+        if let Some(min_neighbors) = self.min_neighbors { params.min_neighbors = min_neighbors; }
+        if let Some(min_size) = self.min_size { params.min_size = min_size; }
+        if let Some(max_size) = self.max_size { params.max_size = max_size; }
+        if let Some(blur) = self.blur { params.blur = blur; }
+        if let Some(dilate) = self.dilate { params.dilate = dilate; }
+        if let Some(close) = self.close { params.close = close; }
+        params
+    }
+
+    /// Builds the detector, installed cascades take precedence over files with the same name.
+    fn detector(&self, backend: Backend) -> Result<HaarCascade> {
+        let library = Library::load()?;
+        let xml = match &self.cascade {
+            Some(name) => match library.find(name) {
+                Some(cascade) => library.path(cascade)?,
+                None if Path::new(name).is_file() => PathBuf::from(name),
+                None => anyhow::bail!("There is no cascade named {name}, nor a file at that path"),
+            },
+            None => match library.last_used() {
+                Some(cascade) => library.path(cascade)?,
+                None => return Ok(HaarCascade::new(self.params()).with_backend(backend)),
+            },
+        };
+
+        Ok(HaarCascade::new(self.params()).with_cascade(xml).with_backend(backend))
+    }
+
+    /// Makes the cascade picked by name the default, once it counted the cars.
+    fn remember(&self) {
+        let name = match &self.cascade {
+            Some(name) => name,
+            None => return,
+        };
+        let remembered = Library::load().and_then(|mut library| match library.find(name) {
+            Some(_) => library.set_last_used(name),
+            None => Ok(()),
+        });
+        if let Err(err) = remembered {
+            eprintln!("Warning: the cascade {name} was not made the default: {err:#}");
+        }
+    }
+}
+
+/// Diff & Connect parameters, each flag overrides the value of the preset.
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Diff & Connect")]
+pub struct DiffArgs {
+    /// Starting values for the parameters
+    #[arg(long, default_value = "default", value_parser = clap::builder::PossibleValuesParser::new(DiffParams::PRESETS))]
+    preset: String,
+    /// Longest side of the working copy of the images, in pixels
+    #[arg(long)]
+    work_size: Option<i32>,
+    /// Size of the Gaussian blur kernel, must be odd
+    #[arg(long)]
+    blur: Option<i32>,
+    /// Gray level that separates black from white, from 0 to 255
+    #[arg(long)]
+    threshold: Option<i32>,
+    /// Size of the opening and dilation kernel, as ROWSxCOLUMNS
+    #[arg(long, value_parser = parse_size)]
+    kernel: Option<(i32, i32)>,
+    /// Times the dilation is applied
+    #[arg(long)]
+    dilate_iterations: Option<i32>,
+}
+
+impl DiffArgs {
+    fn params(&self) -> DiffParams {
+        let mut params = DiffParams::preset(&self.preset).unwrap_or_default();
+        if let Some(work_size) = self.work_size { params.work_size = work_size; }
+        if let Some(blur) = self.blur { params.blur = blur; }
+        if let Some(threshold) = self.threshold { params.threshold = threshold; }
+        if let Some(kernel) = self.kernel { params.kernel = kernel; }
+        if let Some(dilate_iterations) = self.dilate_iterations { params.dilate_iterations = dilate_iterations; }
+        params
+    }
+}
+
+fn parse_backend(value: &str) -> Result<Backend> {
+    Backend::from_id(value).ok_or_else(|| {
+        let ids: Vec<&str> = Backend::ALL.iter().map(|backend| backend.id()).collect();
+        anyhow::anyhow!("Expected one of {}", ids.join(", "))
+    })
+}
+
+fn parse_estimator(value: &str) -> Result<Estimator> {
+    Estimator::from_id(value).ok_or_else(|| {
+        let ids: Vec<&str> = Estimator::ALL.iter().map(|estimator| estimator.id()).collect();
+        anyhow::anyhow!("Expected one of {}", ids.join(", "))
+    })
+}
+
+fn parse_line(value: &str) -> Result<CountingLine> {
+    let coordinates = value.split(',')
+        .map(|coordinate| coordinate.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match coordinates[..] {
+        [x1, y1, x2, y2] => Ok(CountingLine::new((x1, y1), (x2, y2))),
+        _ => anyhow::bail!("Expected a line like 0,300,640,300"),
+    }
+}
+
+fn parse_size(value: &str) -> Result<(i32, i32)> {
+    let (width, height) = value.split_once('x')
+        .ok_or_else(|| anyhow::anyhow!("Expected a size like 30x30"))?;
+    Ok((width.trim().parse()?, height.trim().parse()?))
+}
+
+pub fn run(command: Command, backend: Backend, roi: Option<&Path>) -> Result<()> {
+    let regions = match roi {
+        Some(roi) => roi::load(existing(roi)?)?,
+        None => vec![],
+    };
+
+    match command {
+        Command::Haar { image, output, haar } => {
+            let image = existing(&image)?;
+            let detector = haar.detector(backend)?.with_regions(regions);
+            count(&detector, Input::Single(image), output.as_deref(), image, &detector.regions)?;
+            haar.remember();
+            Ok(())
+        }
+        Command::Diff { before, after, output, diff } => {
+            let (before, after) = (existing(&before)?, existing(&after)?);
+            let detector = DiffConnect::new(diff.params()).with_backend(backend).with_regions(regions);
+            count(&detector, Input::Pair(before, after), output.as_deref(), before, &detector.regions)
+        }
+        Command::Batch { method: BatchMethod::Haar { batch: args, haar } } => {
+            batch(&haar.detector(backend)?.with_regions(regions.clone()), &args, &regions)?;
+            haar.remember();
+            Ok(())
+        }
+        Command::Batch { method: BatchMethod::Diff { batch: args, diff } } => {
+            batch(&DiffConnect::new(diff.params()).with_backend(backend).with_regions(regions.clone()), &args, &regions)
+        }
+        Command::Video { method: VideoMethod::Haar { video: args, haar } } => {
+            count_frames(&haar.detector(backend)?.with_regions(regions.clone()), &args, None, &regions)?;
+            haar.remember();
+            Ok(())
+        }
+        Command::Video { method: VideoMethod::Diff { video: args, reference, diff } } => {
+            let reference = reference.as_deref().map(existing).transpose()?;
+            count_frames(&DiffConnect::new(diff.params()).with_backend(backend).with_regions(regions.clone()), &args, reference, &regions)
+        }
+        Command::Background { source, image, output, estimator, rate, diff } => {
+            let frames = batch::collect(&source)?;
+            let frames: Vec<&Path> = frames.iter().map(PathBuf::as_path).collect();
+            let output = match output {
+                Some(output) => output,
+                None => background::default_output(&source, estimator)?,
+            };
+            background::save(&frames, &BackgroundParams { estimator, rate }, backend, &output)?;
+            eprintln!("Saved the background to {}", output.display());
+
+            match image {
+                Some(image) => {
+                    let image = existing(&image)?;
+                    let detector = DiffConnect::new(diff.params()).with_backend(backend).with_regions(regions);
+                    count(&detector, Input::Pair(image, &output), None, image, &detector.regions)
+                }
+                None => Ok(()),
+            }
+        }
+        Command::Cascades { command } => manage_cascades(command),
+        Command::History => list_history(),
+        Command::Doctor => doctor(),
+    }
+}
+
+fn existing(path: &Path) -> Result<&Path> {
+    if !path.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    Ok(path)
+}
+
+fn extension(path: &Path) -> Result<&str> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| anyhow::anyhow!("{} has no extension", path.display()))
+}
+
+/// Runs the detector and prints the amount of cars, then the cars of each include region,
+/// writing the annotated image to `output`.
+///
+/// The run is also kept in the [`history`], failing to keep it is only a warning.
+fn count(detector: &dyn Detector, input: Input, output: Option<&Path>, first: &Path, regions: &[Region]) -> Result<()> {
+    let ext = match output {
+        Some(output) => extension(output)?,
+        None => extension(first)?,
+    };
+
+    let outcome = detector.detect(input, ext)?;
+    println!("{}", outcome.count());
+    for region in roi::counts(regions, &outcome.detections) {
+        println!("{}: {}", region.name, region.count);
+    }
+
+    // The requested output comes first, a history that can't be written doesn't lose it
+    if let Some(output) = output {
+        std::fs::write(output, &outcome.image)
+            .with_context(|| format!("Could not write {}", output.display()))?;
+    }
+
+    match history::save(detector, &input.paths(), ext, &outcome).and_then(|run| run.dir()) {
+        Ok(dir) => eprintln!("Saved the run to {}", dir.display()),
+        Err(err) => eprintln!("Warning: the run was not kept in the history: {err:#}"),
+    }
+
+    Ok(())
+}
+
+/// Runs the detector over every image of the source and prints a table with the counts.
+///
+/// With `--track` the images are taken as consecutive frames and the cars seen are counted once.
+/// Each include region of `regions` gets a column.
+fn batch(detector: &dyn Detector, args: &BatchArgs, regions: &[Region]) -> Result<()> {
+    let images = batch::collect(&args.source)?;
+    let save_in = match &args.output_dir {
+        Some(output_dir) => {
+            std::fs::create_dir_all(output_dir)?;
+            output_dir.to_path_buf()
+        }
+        None => batch::output_dir()?,
+    };
+    let outputs = batch::outputs(&images, &save_in);
+    batch::check_outputs(&images, &outputs)?;
+
+    let rows: Vec<batch::Row> = batch::jobs(detector.kind(), &images)
+        .into_iter()
+        .zip(&outputs)
+        .map(|(inputs, output)| batch::run(detector, inputs, output))
+        .collect();
+
+    let names: Vec<String> = rows.iter().map(|row| row.name()).collect();
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0).max("File".len());
+
+    // Region columns are as wide as their names
+    let region_names: Vec<&str> = roi::includes(regions).map(|region| region.name.as_str()).collect();
+    let columns = |counts: &[usize]| -> String {
+        counts.iter().zip(&region_names).map(|(count, name)| format!("  {count:>w$}", w = name.len().max(5))).collect()
+    };
+    let header: String = region_names.iter().map(|name| format!("  {name:>5}")).collect();
+
+    println!("{:width$}  {:>8}  Count{header}", "File", "Time");
+    let mut total = 0;
+    let mut region_totals = vec![0; region_names.len()];
+    for (row, name) in rows.iter().zip(&names) {
+        let time = format!("{}ms", row.elapsed.as_millis());
+        match &row.outcome {
+            Ok(outcome) => {
+                total += outcome.count();
+                let counts: Vec<usize> = roi::counts(regions, &outcome.detections).into_iter().map(|region| region.count).collect();
+                region_totals.iter_mut().zip(&counts).for_each(|(total, count)| *total += count);
+                let line = format!("{name:width$}  {time:>8}  {:<5}{}", outcome.count(), columns(&counts));
+                println!("{}", line.trim_end());
+            }
+            Err(err) => println!("{name:width$}  {time:>8}  Error: {err}"),
+        }
+    }
+    let elapsed: std::time::Duration = rows.iter().map(|row| row.elapsed).sum();
+    let line = format!("{:width$}  {:>8}  {total:<5}{}", "Total", format!("{}ms", elapsed.as_millis()), columns(&region_totals));
+    println!("{}", line.trim_end());
+
+    if let Some(params) = args.track.params() {
+        // Images that failed count as frames without cars
+        let frames: Vec<_> = rows.iter()
+            .map(|row| row.outcome.as_ref().map(|outcome| outcome.detections.clone()).unwrap_or_default())
+            .collect();
+        println!("{:width$}  {:>8}  {}", "Unique", "", track::track(&frames, &params)?.unique());
+    }
+    eprintln!("The annotated images are in {}", save_in.display());
+
+    Ok(())
+}
+
+/// Runs the detector on every frame of the video and prints, as CSV, the count of each frame
+/// and include region, the tracks or the crossings of the counting line.
+fn count_frames(detector: &dyn Detector, args: &VideoArgs, reference: Option<&Path>, regions: &[Region]) -> Result<()> {
+    let video = existing(&args.video)?;
+
+    // Fail before reading the whole video
+    if let Some(counting_line) = &args.line {
+        counting_line.validate()?;
+        line::check_bucket(args.bucket)?;
+    }
+
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => video::default_output(video)?,
+    };
+    let outcome = match detector.detect_video(video, reference, &output) {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            // Only removes the run folder made for the default output
+            let _ = history::discard_video(&output);
+            return Err(err.into());
+        }
+    };
+    match history::save_video(detector, video, reference, &outcome).and_then(|run| run.dir()) {
+        Ok(dir) => eprintln!("Saved the run to {}", dir.display()),
+        Err(err) => eprintln!("Warning: the run was not kept in the history: {err:#}"),
+    }
+    match (&args.line, args.track.params()) {
+        (Some(counting_line), _) => {
+            let tracking = track::track(&outcome.frames, &args.track.tracker())?;
+            let crossings = line::crossings(&outcome.frames, &tracking, counting_line)?;
+            let buckets = line::buckets(&crossings, outcome.frames.len(), outcome.fps, args.bucket)?;
+            let (cars_in, cars_out) = line::totals(&buckets);
+            print!("{}", line::to_csv(&buckets));
+            eprintln!("{cars_in} cars in, {cars_out} cars out");
+        }
+        (None, Some(params)) => {
+            let tracking = track::track(&outcome.frames, &params)?;
+            print!("{}", tracking.to_csv(&outcome.frames));
+            eprintln!("{} frames, {} different cars", outcome.frames.len(), tracking.unique());
+        }
+        (None, None) => {
+            print!("{}", roi::to_csv(regions, &outcome));
+            eprintln!("{} frames, at most {} cars, {:.2} on average", outcome.frames.len(), outcome.max(), outcome.mean());
+        }
+    }
+    eprintln!("Saved the annotated video to {}", outcome.output.display());
+
+    Ok(())
+}
+
+fn manage_cascades(command: CascadesCommand) -> Result<()> {
+    let mut library = Library::load()?;
+
+    match command {
+        CascadesCommand::List => {
+            let last_used = library.last_used().map(|cascade| cascade.name.clone()).unwrap_or_default();
+            let width = library.cascades.iter().map(|cascade| cascade.name.len()).max().unwrap_or(0);
+            for cascade in &library.cascades {
+                let mark = if cascade.name == last_used { '*' } else { ' ' };
+                println!("{mark} {:width$}  {}", cascade.name, cascade.description);
+            }
+        }
+        CascadesCommand::Add { xml, name, description } => {
+            let name = match name {
+                Some(name) => name,
+                None => xml.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .ok_or_else(|| anyhow::anyhow!("Use --name to name the cascade"))?,
+            };
+            library.install(&xml, &name, &description)?;
+            println!("Installed {name}");
+        }
+        CascadesCommand::Remove { name } => {
+            library.remove(&name)?;
+            println!("Removed {name}");
+        }
+    }
+
+    Ok(())
+}
+
+fn list_history() -> Result<()> {
+    let runs = history::list()?;
+    let width = runs.iter().map(|run| run.id.len()).max().unwrap_or(0);
+    for run in &runs {
+        println!("{:width$}  {:6}  {:8}  {}", run.id, run.method, run.backend, run.count());
+    }
+    if !runs.is_empty() {
+        println!("\nRuns are kept in {}", history::dir()?.display());
+    }
+    Ok(())
+}
+
+fn doctor() -> Result<()> {
+    let checks = doctor::run();
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+
+    for check in &checks {
+        match &check.status {
+            Ok(found) => println!("ok     {:width$}  {found}", check.name),
+            Err(err) => {
+                println!("error  {:width$}  {err}", check.name);
+                println!("       {:width$}  {}", "", check.hint);
+            }
+        }
+    }
+
+    if !doctor::healthy(&checks) {
+        anyhow::bail!("Some checks failed");
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Backgrounds estimated from many frames of the same scene.
+//!
+//! A car parked in the "empty" reference, or a cloud passing by, shows up as a change when
+//! Diff & Connect compares two images. Estimating the background from a sequence of frames
+//! removes what only appears in a few of them, the estimate is then used as the reference.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::{ImageOutputFormat, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use super::{error::check_sizes, load, Backend, DetectError};
+
+/// How the background is estimated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Estimator {
+    /// Median of each pixel, ignores anything present in less than half of the frames.
+    Median,
+    /// Running average, recent frames weigh more.
+    Average,
+    /// OpenCV's Gaussian mixture background subtractor, only on the OpenCV backends.
+    Mog2,
+}
+
+impl Estimator {
+    pub const ALL: &'static [Estimator] = &[Estimator::Median, Estimator::Average, Estimator::Mog2];
+
+    /// Short unique identifier, used by the CLI.
+    pub fn id(self) -> &'static str {
+        match self {
+            Estimator::Median => "median",
+            Estimator::Average => "average",
+            Estimator::Mog2 => "mog2",
+        }
+    }
+
+    /// Name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Estimator::Median => "Temporal median",
+            Estimator::Average => "Running average",
+            Estimator::Mog2 => "MOG2",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Estimator::ALL.iter().copied().find(|estimator| estimator.id() == id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackgroundParams {
+    pub estimator: Estimator,
+    /// Weight of each new frame in the running average, from 0 to 1.
+    pub rate: f64,
+}
+
+impl Default for BackgroundParams {
+    fn default() -> Self {
+        BackgroundParams { estimator: Estimator::Median, rate: 0.05 }
+    }
+}
+
+impl BackgroundParams {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.rate > 0. && self.rate <= 1.) {
+            anyhow::bail!("The rate must be greater than 0 and at most 1");
+        }
+        Ok(())
+    }
+}
+
+/// Estimates the background of `frames`, which must all have the same size, encoded as PNG.
+///
+/// The median keeps every frame in memory, the other estimators only keep one at a time.
+pub fn estimate(frames: &[&Path], params: &BackgroundParams, backend: Backend) -> Result<Vec<u8>, DetectError> {
+    params.validate()?;
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("The background needs at least one frame").into());
+    }
+
+    let background = match params.estimator {
+        Estimator::Median => median(frames)?,
+        Estimator::Average => average(frames, params.rate)?,
+        Estimator::Mog2 => return mog2(frames, backend),
+    };
+    encode(&background)
+}
+
+/// Estimates the background of `frames` and writes it to `output` as PNG.
+pub fn save(frames: &[&Path], params: &BackgroundParams, backend: Backend, output: &Path) -> Result<(), DetectError> {
+    let background = estimate(frames, params, backend)?;
+    std::fs::write(output, background)
+        .map_err(|err| DetectError::WriteFailure(format!("{} ({err})", output.display())))
+}
+
+/// Directory where estimated backgrounds are kept unless another path is given.
+pub fn dir() -> Result<PathBuf> {
+    let dir = super::data_dir()?.join("backgrounds");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Default path of the background of the frames in `source`, named after their folder.
+pub fn default_output(source: &Path, estimator: Estimator) -> Result<PathBuf> {
+    let folder = if source.is_dir() { Some(source) } else { source.parent() };
+    let name = folder
+        .and_then(|folder| folder.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "background".to_owned());
+    Ok(dir()?.join(format!("{name}_{}.png", estimator.id())))
+}
+
+fn decode(frame: &Path) -> Result<RgbImage, DetectError> {
+    image::load_from_memory(&load(frame)?)
+        .map(|img| img.into_rgb8())
+        .map_err(|_| DetectError::UnreadableImage(frame.display().to_string()))
+}
+
+fn encode(background: &RgbImage) -> Result<Vec<u8>, DetectError> {
+    let mut encoded = std::io::Cursor::new(vec![]);
+    background.write_to(&mut encoded, ImageOutputFormat::Png)
+        .map_err(|_| DetectError::EncodeFailure("png".to_owned()))?;
+    Ok(encoded.into_inner())
+}
+
+/// Decodes every frame, failing if one doesn't have the size of the first.
+fn decode_all(frames: &[&Path]) -> Result<Vec<RgbImage>, DetectError> {
+    let mut decoded: Vec<RgbImage> = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let img = decode(frame)?;
+        if let Some(first) = decoded.first() {
+            check_sizes(first.dimensions(), img.dimensions())?;
+        }
+        decoded.push(img);
+    }
+    Ok(decoded)
+}
+
+fn median(frames: &[&Path]) -> Result<RgbImage, DetectError> {
+    let decoded = decode_all(frames)?;
+    let (width, height) = decoded[0].dimensions();
+
+    let mut background = RgbImage::new(width, height);
+    let mut values = Vec::with_capacity(decoded.len());
+    for (i, channel) in background.iter_mut().enumerate() {
+        values.clear();
+        values.extend(decoded.iter().map(|frame| frame.as_raw()[i]));
+        let middle = values.len() / 2;
+        *channel = *values.select_nth_unstable(middle).1;
+    }
+    Ok(background)
+}
+
+fn average(frames: &[&Path], rate: f64) -> Result<RgbImage, DetectError> {
+    let first = decode(frames[0])?;
+    let (width, height) = first.dimensions();
+
+    let rate = rate as f32;
+    let mut sum: Vec<f32> = first.as_raw().iter().map(|&channel| channel as f32).collect();
+    for frame in &frames[1..] {
+        let img = decode(frame)?;
+        check_sizes((width, height), img.dimensions())?;
+        for (acc, &channel) in sum.iter_mut().zip(img.as_raw()) {
+            *acc += rate * (channel as f32 - *acc);
+        }
+    }
+
+    let pixels = sum.into_iter().map(|channel| channel.round().clamp(0., 255.) as u8).collect();
+    let background = RgbImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow::anyhow!("The background doesn't have the size of the frames"))?;
+    Ok(background)
+}
+
+fn mog2(frames: &[&Path], backend: Backend) -> Result<Vec<u8>, DetectError> {
+    let background: Result<Vec<u8>> = match backend {
+        #[cfg(feature = "opencv-metal")]
+        Backend::Metal => super::metal::mog2_background(frames),
+        #[cfg(feature = "opencv-python")]
+        Backend::Python => super::python::mog2_background(frames),
+        #[cfg(feature = "native")]
+        Backend::Native => super::native::mog2_background(frames),
+    };
+    Ok(background?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::fixtures::{self, TempImage};
+    use image::Rgb;
+
+    /// Flat gray frame with a bright car at `car`.
+    fn frame(name: &str, size: (u32, u32), car: Option<(u32, u32)>) -> TempImage {
+        let img = RgbImage::from_fn(size.0, size.1, |x, y| match car {
+            Some(car) if (car.0..car.0 + 4).contains(&x) && (car.1..car.1 + 2).contains(&y) => Rgb([230, 20, 230]),
+            _ => Rgb([60, 90, 120]),
+        });
+        fixtures::png(&format!("background-{name}"), &img)
+    }
+
+    fn paths(frames: &[TempImage]) -> Vec<&Path> {
+        frames.iter().map(TempImage::path).collect()
+    }
+
+    #[test]
+    fn median_removes_passing_cars() {
+        // The car drives along the road, each pixel is covered in less than half of the frames
+        let frames: Vec<_> = [None, Some((0, 4)), Some((3, 4)), Some((6, 4)), Some((9, 4))].iter().enumerate()
+            .map(|(i, &car)| frame(&format!("median{i}"), (16, 8), car))
+            .collect();
+
+        let background = median(&paths(&frames)).unwrap();
+        assert_eq!(background.dimensions(), (16, 8));
+        assert!(background.pixels().all(|&pixel| pixel == Rgb([60, 90, 120])));
+    }
+
+    #[test]
+    fn median_keeps_parked_cars() {
+        let frames: Vec<_> = [Some((2, 2)), Some((2, 2)), None].iter().enumerate()
+            .map(|(i, &car)| frame(&format!("parked{i}"), (16, 8), car))
+            .collect();
+
+        let background = median(&paths(&frames)).unwrap();
+        assert_eq!(*background.get_pixel(3, 3), Rgb([230, 20, 230]));
+        assert_eq!(*background.get_pixel(10, 3), Rgb([60, 90, 120]));
+    }
+
+    #[test]
+    fn average_weighs_recent_frames() {
+        let frames = [frame("average0", (8, 4), None), frame("average1", (8, 4), Some((0, 0)))];
+
+        let background = average(&paths(&frames), 0.5).unwrap();
+        assert_eq!(*background.get_pixel(0, 0), Rgb([145, 55, 175]));
+        assert_eq!(*background.get_pixel(7, 3), Rgb([60, 90, 120]));
+    }
+
+    #[test]
+    fn frames_must_have_one_size() {
+        let frames = [frame("size0", (8, 4), None), frame("size1", (4, 8), None)];
+
+        let mismatch = |result: Result<RgbImage, DetectError>| matches!(result, Err(DetectError::SizeMismatch { first: (8, 4), second: (4, 8) }));
+        assert!(mismatch(median(&paths(&frames))));
+        assert!(mismatch(average(&paths(&frames), 0.5)));
+    }
+
+    #[test]
+    fn rejects_bad_rates() {
+        assert!(BackgroundParams::default().validate().is_ok());
+        assert!(BackgroundParams { rate: 0., ..BackgroundParams::default() }.validate().is_err());
+        assert!(BackgroundParams { rate: f64::NAN, ..BackgroundParams::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Running a [`Detector`] over many images.
+
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use anyhow::Result;
+
+use super::{history, DetectError, Detector, Input, Kind, Outcome};
+
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// Lists the images of a directory, or the files matching a glob pattern, sorted by name.
+pub fn collect(source: &Path) -> Result<Vec<PathBuf>> {
+    let mut images: Vec<PathBuf> = if source.is_dir() {
+        std::fs::read_dir(source)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && is_image(path))
+            .collect()
+    } else {
+        let pattern = source.to_str()
+            .ok_or_else(|| anyhow::anyhow!("The pattern {} is not valid UTF-8", source.display()))?;
+        glob::glob(pattern)?
+            .filter_map(|path| path.ok())
+            .filter(|path| path.is_file() && is_image(path))
+            .collect()
+    };
+
+    images.sort();
+    if images.is_empty() {
+        anyhow::bail!("No images found in {}", source.display());
+    }
+    Ok(images)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Groups the images into the inputs of a detector of the given kind.
+///
+/// Pair detectors compare every image with the next one.
+pub fn jobs(kind: Kind, images: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    match kind {
+        Kind::Single => images.iter().map(|img| vec![img.clone()]).collect(),
+        Kind::Pair => images.windows(2).map(|pair| pair.to_vec()).collect(),
+    }
+}
+
+/// Where the annotated image of each of `images` is written in `save_in`.
+///
+/// The images keep their path from the folder they are all in, so images of different folders
+/// with the same name, as a pattern like `**/*.jpg` finds them, don't replace each other.
+pub fn outputs(images: &[PathBuf], save_in: &Path) -> Vec<PathBuf> {
+    // Resolved so `..` can't lead out of `save_in`
+    let resolved: Vec<PathBuf> = images.iter()
+        .map(|img| img.canonicalize().unwrap_or_else(|_| img.clone()))
+        .collect();
+    let mut root = resolved.first().and_then(|img| img.parent()).map(Path::to_path_buf).unwrap_or_default();
+    for img in resolved.iter().skip(1) {
+        while !img.starts_with(&root) {
+            if !root.pop() {
+                break;
+            }
+        }
+    }
+
+    resolved.iter().zip(images).map(|(img, original)| {
+        match img.strip_prefix(&root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => save_in.join(relative),
+            _ => save_in.join(original.file_name().unwrap_or(original.as_os_str())),
+        }
+    }).collect()
+}
+
+/// Result of one job of the batch.
+#[derive(Debug)]
+pub struct Row {
+    pub inputs: Vec<PathBuf>,
+    pub outcome: Result<Outcome>,
+    /// How long the detection took.
+    pub elapsed: Duration,
+}
+
+impl Row {
+    /// Names of the input files, separated by an arrow for pairs.
+    pub fn name(&self) -> String {
+        self.inputs.iter()
+            .map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// Runs one job, the annotated image of the first input is written to `output`, one of the
+/// [`outputs`].
+pub fn run(detector: &dyn Detector, inputs: Vec<PathBuf>, output: &Path) -> Row {
+    let started = Instant::now();
+    let outcome = run_job(detector, &inputs, output);
+    Row { inputs, outcome, elapsed: started.elapsed() }
+}
+
+fn run_job(detector: &dyn Detector, inputs: &[PathBuf], output: &Path) -> Result<Outcome> {
+    let paths: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+    let input = Input::new(detector.kind(), &paths)
+        .ok_or_else(|| anyhow::anyhow!("{} expects {} images", detector.name(), detector.kind().inputs()))?;
+
+    let first = &inputs[0];
+    check_outputs(&inputs[..1], &[output.to_path_buf()])?;
+    let ext = first.extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| anyhow::anyhow!("{} has no extension", first.display()))?;
+
+    let outcome = detector.detect(input, ext)?;
+
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| DetectError::WriteFailure(format!("{} ({err})", dir.display())))?;
+    }
+    std::fs::write(output, &outcome.image)
+        .map_err(|err| DetectError::WriteFailure(format!("{} ({err})", output.display())))?;
+
+    Ok(outcome)
+}
+
+/// Fails if writing the annotated images to their `outputs` would replace some of the `images`.
+pub fn check_outputs(images: &[PathBuf], outputs: &[PathBuf]) -> Result<()> {
+    for (image, output) in images.iter().zip(outputs) {
+        let dir = |path: &Path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize().ok(),
+            _ => Path::new(".").canonicalize().ok(),
+        };
+        // Folders that don't exist yet can't hold any of the images
+        let output_dir = match dir(output) {
+            Some(output_dir) => output_dir,
+            None => continue,
+        };
+        if dir(image).as_ref() == Some(&output_dir) {
+            anyhow::bail!("The annotated images would replace the images in {}, pick another output directory", output_dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// New directory for the annotated images of a batch, inside `batch/` in the data directory
+/// and named after the time it started.
+pub fn output_dir() -> Result<PathBuf> {
+    let dir = super::data_dir()?.join("batch");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    let (_, _, batch_dir) = history::timestamped_dir(&dir)?;
+    Ok(batch_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty folder in the temp directory, removed by the test.
+    fn folder(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("imp-batch-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn patterns_only_match_images() {
+        let dir = folder("pattern");
+        for name in ["a.png", "b.JPG", "notes.txt", "c.png.bak"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let images = collect(&dir.join("*"));
+        let listed = collect(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(images.unwrap(), vec![dir.join("a.png"), dir.join("b.JPG")]);
+        assert_eq!(listed.unwrap(), vec![dir.join("a.png"), dir.join("b.JPG")]);
+    }
+
+    #[test]
+    fn outputs_keep_the_folders_of_the_images() {
+        let dir = folder("outputs");
+        for sub in ["north", "south/cam"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+            std::fs::write(dir.join(sub).join("frame.png"), b"").unwrap();
+        }
+        let images = collect(&dir.join("**/*.png"));
+        let save_in = Path::new("out");
+        let outputs = images.as_ref().map(|images| (outputs(images, save_in), outputs(&images[..1], save_in)));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (both, one) = outputs.unwrap();
+        assert_eq!(both, vec![save_in.join("north/frame.png"), save_in.join("south/cam/frame.png")]);
+        assert_eq!(one, vec![save_in.join("frame.png")]);
+    }
+
+    #[test]
+    fn refuses_to_replace_the_images() {
+        let dir = folder("replace");
+        let images = vec![dir.join("a.png")];
+        std::fs::write(&images[0], b"").unwrap();
+        let replaced = check_outputs(&images, &outputs(&images, &dir));
+        let elsewhere = check_outputs(&images, &outputs(&images, &dir.join("out")));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(replaced.is_err());
+        assert!(elsewhere.is_ok());
+    }
+}
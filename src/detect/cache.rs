@@ -0,0 +1,39 @@
+//! Models loaded once and reused by later detections.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use anyhow::Result;
+
+/// Values loaded from files, reloaded when the file is modified.
+pub struct FileCache<T> {
+    entries: Mutex<Vec<(PathBuf, Option<SystemTime>, T)>>,
+}
+
+impl<T> FileCache<T> {
+    pub const fn new() -> Self {
+        FileCache { entries: Mutex::new(Vec::new()) }
+    }
+
+    /// Runs `f` on the value of `path`, calling `load` first if it isn't cached or the file changed.
+    pub fn with<R>(&self, path: &Path, load: impl FnOnce(&Path) -> Result<T>, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(index) = entries.iter().position(|(cached, _, _)| cached == path) {
+            let (_, cached_modified, value) = &mut entries[index];
+            if modified.is_some() && *cached_modified == modified {
+                return Ok(f(value));
+            }
+            entries.remove(index);
+        }
+
+        let mut value = load(path)?;
+        let result = f(&mut value);
+        entries.push((path.to_path_buf(), modified, value));
+        Ok(result)
+    }
+}
@@ -0,0 +1,209 @@
+//! Library of OpenCV cascade models installed in the data directory.
+//!
+//! The models live in `cascades/` inside the data directory, next to a `library.json`
+//! listing their names and descriptions and the last one used. The embedded `cars.xml`
+//! is always available under the name `cars`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const BUILTIN: &str = "cars";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cascade {
+    pub name: String,
+    pub description: String,
+    /// File name of the model inside the cascades directory.
+    pub file: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Library {
+    pub cascades: Vec<Cascade>,
+    pub last_used: Option<String>,
+}
+
+/// Directory with the installed models.
+pub fn dir() -> Result<PathBuf> {
+    let dir = super::data_dir()?.join("cascades");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Path to the embedded `cars.xml`, writing it to the cascades directory on first use.
+pub fn builtin_path() -> Result<PathBuf> {
+    let xml = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/cars.xml"));
+    let xml_path = dir()?.join("cars.xml");
+
+    if !xml_path.exists() {
+        std::fs::write(&xml_path, xml)?;
+    }
+
+    Ok(xml_path)
+}
+
+/// Fails unless `xml` is a cascade in one of the formats OpenCV reads.
+fn check(xml: &str) -> Result<()> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("opencv_storage") {
+        anyhow::bail!("The file has no <opencv_storage>");
+    }
+    let classifier = root.children()
+        .find(|n| n.is_element())
+        .ok_or_else(|| anyhow::anyhow!("The file is empty"))?;
+
+    let required: &[&str] = match classifier.attribute("type_id") {
+        Some("opencv-haar-classifier") => &["size", "stages"],
+        Some("opencv-cascade-classifier") => &["stageType", "featureType", "width", "height", "stages"],
+        _ => anyhow::bail!("<{}> is not a cascade classifier", classifier.tag_name().name()),
+    };
+    for name in required {
+        if !classifier.children().any(|n| n.has_tag_name(*name)) {
+            anyhow::bail!("Missing <{name}> in <{}>", classifier.tag_name().name());
+        }
+    }
+    let stages = classifier.children().find(|n| n.has_tag_name("stages")).into_iter()
+        .flat_map(|stages| stages.children())
+        .filter(|n| n.has_tag_name("_"))
+        .count();
+    if stages == 0 {
+        anyhow::bail!("The cascade has no stages");
+    }
+
+    // The native backend reads the old format itself, catch what it would fail on now
+    #[cfg(feature = "native")]
+    if classifier.attribute("type_id") == Some("opencv-haar-classifier") {
+        super::native::Cascade::parse(xml)?;
+    }
+    Ok(())
+}
+
+impl Library {
+    /// Reads the library, always including the embedded model.
+    pub fn load() -> Result<Self> {
+        let index = dir()?.join("library.json");
+        let mut library: Library = if index.exists() {
+            serde_json::from_str(&std::fs::read_to_string(index)?)?
+        } else {
+            Library::default()
+        };
+
+        builtin_path()?;
+        if library.find(BUILTIN).is_none() {
+            library.cascades.insert(0, Cascade {
+                name: BUILTIN.to_owned(),
+                description: "Cars seen from above, embedded with the app".to_owned(),
+                file: "cars.xml".to_owned(),
+            });
+        }
+
+        Ok(library)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(dir()?.join("library.json"), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Cascade> {
+        self.cascades.iter().find(|cascade| cascade.name == name)
+    }
+
+    pub fn path(&self, cascade: &Cascade) -> Result<PathBuf> {
+        Ok(dir()?.join(&cascade.file))
+    }
+
+    /// The last model used, or the embedded one.
+    pub fn last_used(&self) -> Option<&Cascade> {
+        self.last_used.as_deref()
+            .and_then(|name| self.find(name))
+            .or_else(|| self.find(BUILTIN))
+    }
+
+    pub fn set_last_used(&mut self, name: &str) -> Result<()> {
+        if self.find(name).is_none() {
+            anyhow::bail!("There is no cascade named {name}");
+        }
+        self.last_used = Some(name.to_owned());
+        self.save()
+    }
+
+    /// Copies the model at `path` into the library, replacing any model with the same name.
+    pub fn install(&mut self, path: &Path, name: &str, description: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_')) {
+            anyhow::bail!("Cascade names can only have letters, numbers, '-' and '_'");
+        }
+        if name == BUILTIN {
+            anyhow::bail!("The {BUILTIN} cascade can't be replaced");
+        }
+
+        let xml = std::fs::read_to_string(path)?;
+        check(&xml).with_context(|| format!("{} is not an OpenCV cascade", path.display()))?;
+
+        let file = format!("{name}.xml");
+        std::fs::write(dir()?.join(&file), xml)?;
+
+        self.cascades.retain(|cascade| cascade.name != name);
+        self.cascades.push(Cascade {
+            name: name.to_owned(),
+            description: description.trim().to_owned(),
+            file,
+        });
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        if name == BUILTIN {
+            anyhow::bail!("The {BUILTIN} cascade can't be removed");
+        }
+        let cascade = self.find(name)
+            .ok_or_else(|| anyhow::anyhow!("There is no cascade named {name}"))?
+            .clone();
+
+        std::fs::remove_file(self.path(&cascade)?)?;
+        self.cascades.retain(|c| c.name != name);
+        if self.last_used.as_deref() == Some(name) {
+            self.last_used = None;
+        }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_the_builtin_cascade() {
+        check(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/cars.xml"))).unwrap();
+    }
+
+    #[test]
+    fn accepts_the_new_format() {
+        let xml = r#"<?xml version="1.0"?>
+<opencv_storage>
+<cascade type_id="opencv-cascade-classifier">
+  <stageType>BOOST</stageType>
+  <featureType>HAAR</featureType>
+  <height>24</height>
+  <width>24</width>
+  <stages>
+    <_><maxWeakCount>1</maxWeakCount></_></stages></cascade>
+</opencv_storage>"#;
+        check(xml).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(check("not xml").is_err());
+        assert!(check("<opencv_storage></opencv_storage>").is_err());
+        assert!(check(r#"<opencv_storage><calibration type_id="opencv-matrix"/></opencv_storage>"#).is_err());
+        assert!(check(r#"<opencv_storage><cars type_id="opencv-haar-classifier"><size>20 20</size><stages/></cars></opencv_storage>"#).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Checks that the environment the backends need is in place.
+
+use super::Backend;
+
+/// Result of one check.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    /// What was found, or why the check failed.
+    pub status: Result<String, String>,
+    /// How to fix a failed check.
+    pub hint: &'static str,
+}
+
+impl Check {
+    pub fn new(name: &'static str, status: Result<String, String>, hint: &'static str) -> Self {
+        Check { name, status, hint }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.status.is_ok()
+    }
+}
+
+/// Runs every check of the enabled backends.
+pub fn run() -> Vec<Check> {
+    let checks = vec![
+        Check::new(
+            "Data directory",
+            super::data_dir()
+                .map(|dir| dir.display().to_string())
+                .map_err(|err| err.to_string()),
+            "Make sure the user can write to its parent directory",
+        ),
+        Check::new(
+            "Backends",
+            Ok(Backend::ALL.iter().map(|backend| backend.name()).collect::<Vec<_>>().join(", ")),
+            "",
+        ),
+    ];
+
+    #[cfg(feature = "opencv-metal")]
+    let checks = [checks, super::metal::diagnose()].concat();
+    #[cfg(feature = "opencv-python")]
+    let checks = [checks, super::python::diagnose()].concat();
+
+    checks
+}
+
+/// Whether every check passed.
+pub fn healthy(checks: &[Check]) -> bool {
+    checks.iter().all(Check::passed)
+}
@@ -0,0 +1,67 @@
+//! Failures of a detection the user can act on.
+
+use thiserror::Error;
+
+/// Formats every backend can write the annotated image in.
+pub const OUTPUT_FORMATS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tif", "tiff"];
+
+#[derive(Debug, Error)]
+pub enum DetectError {
+    #[error("Could not read the image {0}")]
+    UnreadableImage(String),
+    #[error("Could not read the video {0}")]
+    UnreadableVideo(String),
+    #[error("Images can't be saved as .{0}, use one of {}", OUTPUT_FORMATS.join(", "))]
+    UnsupportedFormat(String),
+    #[error("Videos can't be saved as .{0}, use one of {}", super::video::OUTPUT_FORMATS.join(", "))]
+    UnsupportedVideoFormat(String),
+    /// The backend, named in the variant, can't decode videos.
+    #[error("The {0} backend can't read videos, pick an OpenCV backend")]
+    VideoUnsupported(&'static str),
+    /// The backend, named in the variant, has no MOG2 background subtractor.
+    #[error("The {0} backend can't estimate backgrounds with MOG2, pick an OpenCV backend")]
+    Mog2Unsupported(&'static str),
+    #[error("Could not load the cascade {0}")]
+    MissingCascade(String),
+    #[error("Python is not available: {0}")]
+    PythonUnavailable(String),
+    /// `cv2` or `numpy` can't be imported.
+    #[error("The Python module {0} is not installed, install it with `pip install opencv-python`")]
+    MissingModule(String),
+    #[error("Could not encode the annotated image as .{0}")]
+    EncodeFailure(String),
+    #[error("Could not save the image to {0}")]
+    WriteFailure(String),
+    #[error("The images have different sizes, {}x{} and {}x{}", .first.0, .first.1, .second.0, .second.1)]
+    SizeMismatch { first: (u32, u32), second: (u32, u32) },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+/// Keeps the variant when the error already is a [`DetectError`].
+impl From<anyhow::Error> for DetectError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<DetectError>() {
+            Ok(err) => err,
+            Err(err) => DetectError::Other(err),
+        }
+    }
+}
+
+/// Fails with [`DetectError::UnsupportedFormat`] if the annotated image can't be saved as `ext`.
+pub fn check_format(ext: &str) -> Result<(), DetectError> {
+    if OUTPUT_FORMATS.contains(&ext.to_lowercase().as_str()) {
+        Ok(())
+    } else {
+        Err(DetectError::UnsupportedFormat(ext.to_owned()))
+    }
+}
+
+/// Fails with [`DetectError::SizeMismatch`] if both images don't have the same size.
+pub fn check_sizes(first: (u32, u32), second: (u32, u32)) -> Result<(), DetectError> {
+    if first == second {
+        Ok(())
+    } else {
+        Err(DetectError::SizeMismatch { first, second })
+    }
+}
@@ -0,0 +1,120 @@
+//! Saving the annotated image of a detection where the user picks.
+
+use std::{io::Cursor, path::{Path, PathBuf}};
+
+use anyhow::Result;
+use image::ImageOutputFormat;
+use serde::Serialize;
+
+use super::{Detection, Outcome};
+
+/// Formats the annotated image can be saved as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Jpeg,
+    /// Lossless WebP.
+    WebP,
+}
+
+impl Format {
+    pub const ALL: &'static [Format] = &[Format::Png, Format::Jpeg, Format::WebP];
+
+    /// Short unique identifier, also the extension of the file.
+    pub fn id(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::WebP => "webp",
+        }
+    }
+
+    /// Name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Png => "PNG",
+            Format::Jpeg => "JPEG",
+            Format::WebP => "WebP",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Format::ALL.iter().copied().find(|format| format.id() == id)
+    }
+
+    /// Format of a file with the extension `ext`, in any case.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "jpeg" => Some(Format::Jpeg),
+            ext => Format::from_id(ext),
+        }
+    }
+
+    /// Media type of the format, as in data URLs.
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::Png => "image/png",
+            Format::Jpeg => "image/jpeg",
+            Format::WebP => "image/webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub format: Format,
+    /// Quality of JPEG images, from 1 to 100.
+    pub quality: u8,
+    /// Also save the detections next to the image, as JSON.
+    pub sidecar: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { format: Format::Png, quality: 90, sidecar: false }
+    }
+}
+
+/// Contents of the JSON sidecar.
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    /// File name of the image the detections belong to.
+    image: String,
+    count: usize,
+    detections: &'a [Detection],
+}
+
+/// Saves the annotated image of `outcome` to `path` and, if asked, its detections.
+///
+/// Returns the path of the sidecar, which has the name of the image with a `.json` extension.
+pub fn save(outcome: &Outcome, path: &Path, options: &Options) -> Result<Option<PathBuf>> {
+    std::fs::write(path, encode(&outcome.image, options)?)?;
+
+    if !options.sidecar {
+        return Ok(None);
+    }
+    let sidecar = Sidecar {
+        image: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        count: outcome.count(),
+        detections: &outcome.detections,
+    };
+    let sidecar_path = path.with_extension("json");
+    std::fs::write(&sidecar_path, serde_json::to_string_pretty(&sidecar)?)?;
+    Ok(Some(sidecar_path))
+}
+
+/// Converts an encoded image to the format of the options.
+fn encode(image: &[u8], options: &Options) -> Result<Vec<u8>> {
+    let quality = options.quality.clamp(1, 100);
+    let format = match options.format {
+        Format::Png => ImageOutputFormat::Png,
+        Format::Jpeg => ImageOutputFormat::Jpeg(quality),
+        Format::WebP => ImageOutputFormat::WebP,
+    };
+
+    // The annotated images have no transparency, JPEG can't store it anyway
+    let img = image::load_from_memory(image)?.into_rgb8();
+    let mut encoded = Cursor::new(vec![]);
+    img.write_to(&mut encoded, format)?;
+    Ok(encoded.into_inner())
+}
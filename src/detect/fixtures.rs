@@ -0,0 +1,35 @@
+//! Images the tests write for the methods to read back.
+
+use std::path::{Path, PathBuf};
+
+use image::RgbImage;
+
+/// PNG in the temp directory, removed once dropped.
+pub struct TempImage(PathBuf);
+
+impl TempImage {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for TempImage {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempImage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Writes `img` as a PNG in the temp directory, `name` must be unique among the tests.
+pub fn png(name: &str, img: &RgbImage) -> TempImage {
+    let path = std::env::temp_dir().join(format!("imp-{}-{name}.png", std::process::id()));
+    img.save(&path).unwrap();
+    TempImage(path)
+}
@@ -0,0 +1,305 @@
+//! Results of past detections.
+//!
+//! Every run gets its own folder inside `runs/` in the data directory, named after the time
+//! it finished. The folder keeps a copy of the inputs, the annotated image, a thumbnail of it
+//! and a `run.json` with the method, its parameters and the detections.
+//!
+//! Runs on a video are named after the time they started, the annotated video is written
+//! straight into their folder. The video itself isn't copied, only its path is kept.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{video, Detection, Detector, Outcome, VideoOutcome};
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Run {
+    /// Name of the folder of the run.
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    /// Id of the [`Detector`] used.
+    pub method: String,
+    /// Id of the [`Backend`](super::Backend) used.
+    pub backend: String,
+    pub params: serde_json::Value,
+    /// File names of the copies of the inputs, in order.
+    pub inputs: Vec<String>,
+    /// File name of the annotated image or video, or its full path when the video was written
+    /// outside the folder of the run.
+    pub output: String,
+    /// File name of the thumbnail, `None` if the annotated image couldn't be shrunk.
+    pub thumbnail: Option<String>,
+    /// Empty for runs on a video, their detections are in `video`.
+    pub detections: Vec<Detection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video: Option<VideoRun>,
+}
+
+/// What a run on a video found.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VideoRun {
+    /// Path of the video counted.
+    pub source: PathBuf,
+    /// Frames per second of the video.
+    pub fps: f64,
+    /// Detections of every frame, in order.
+    pub frames: Vec<Vec<Detection>>,
+}
+
+impl Run {
+    /// Amount of cars found, for videos the most found in a single frame.
+    pub fn count(&self) -> usize {
+        match &self.video {
+            Some(video) => video.frames.iter().map(Vec::len).max().unwrap_or(0),
+            None => self.detections.len(),
+        }
+    }
+
+    /// Folder of the run.
+    pub fn dir(&self) -> Result<PathBuf> {
+        Ok(dir()?.join(&self.id))
+    }
+
+    /// When the run finished, as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub fn date(&self) -> String {
+        let (date, time) = timestamp(self.created);
+        format!("{date} {}", time.replace('-', ":"))
+    }
+}
+
+/// Directory with the folders of every run.
+pub fn dir() -> Result<PathBuf> {
+    let dir = super::data_dir()?.join("runs");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Keeps the inputs and the outcome of a detection in a new folder.
+pub fn save(detector: &dyn Detector, inputs: &[&Path], ext: &str, outcome: &Outcome) -> Result<Run> {
+    let (created, id, run_dir) = timestamped_dir(&dir()?)?;
+    let copies = copy_inputs(&run_dir, inputs)?;
+
+    let output = format!("output.{ext}");
+    std::fs::write(run_dir.join(&output), &outcome.image)?;
+
+    let run = Run {
+        id,
+        created,
+        method: detector.id().to_owned(),
+        backend: detector.backend().id().to_owned(),
+        params: detector.params(),
+        inputs: copies,
+        output,
+        thumbnail: thumbnail(&run_dir, &outcome.image),
+        detections: outcome.detections.clone(),
+        video: None,
+    };
+    std::fs::write(run_dir.join("run.json"), serde_json::to_string_pretty(&run)?)?;
+
+    Ok(run)
+}
+
+/// New folder for a run on a video, the annotated video is written into it before [`save_video`].
+pub fn video_dir() -> Result<PathBuf> {
+    let (_, _, run_dir) = timestamped_dir(&dir()?)?;
+    Ok(run_dir)
+}
+
+/// Keeps a run on a video, in the folder of the annotated video when it's one of [`video_dir`].
+///
+/// Only the reference image is copied, `video` is kept by its path.
+pub fn save_video(detector: &dyn Detector, video: &Path, reference: Option<&Path>, outcome: &VideoOutcome) -> Result<Run> {
+    let created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let (id, run_dir, output) = match run_of(&outcome.output)? {
+        Some((id, run_dir)) => {
+            let output = outcome.output.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            (id, run_dir, output)
+        }
+        None => {
+            let (_, id, run_dir) = timestamped_dir(&dir()?)?;
+            (id, run_dir, outcome.output.display().to_string())
+        }
+    };
+    let copies = copy_inputs(&run_dir, &reference.into_iter().collect::<Vec<_>>())?;
+
+    // The first annotated frame stands for the video
+    let first = video::first_frame(&outcome.output, detector.backend()).unwrap_or_default();
+
+    let run = Run {
+        id,
+        created,
+        method: detector.id().to_owned(),
+        backend: detector.backend().id().to_owned(),
+        params: detector.params(),
+        inputs: copies,
+        output,
+        thumbnail: thumbnail(&run_dir, &first),
+        detections: vec![],
+        video: Some(VideoRun {
+            source: video.to_path_buf(),
+            fps: outcome.fps,
+            frames: outcome.frames.clone(),
+        }),
+    };
+    std::fs::write(run_dir.join("run.json"), serde_json::to_string_pretty(&run)?)?;
+
+    Ok(run)
+}
+
+/// Deletes the folder of [`video_dir`] the annotated video `output` is in, for a run on a video
+/// that failed or was cancelled. Does nothing for videos written elsewhere.
+pub fn discard_video(output: &Path) -> Result<()> {
+    if let Some((_, run_dir)) = run_of(output)? {
+        std::fs::remove_dir_all(run_dir)?;
+    }
+    Ok(())
+}
+
+/// Name and path of the run folder `output` is in, `None` if it's outside the history.
+fn run_of(output: &Path) -> Result<Option<(String, PathBuf)>> {
+    let dir = dir()?;
+    let run_dir = output.parent().filter(|parent| parent.parent() == Some(dir.as_path()));
+    Ok(run_dir.and_then(|run_dir| {
+        let id = run_dir.file_name()?.to_string_lossy().into_owned();
+        Some((id, run_dir.to_path_buf()))
+    }))
+}
+
+/// Copies the inputs into the folder of a run, returning the names of the copies.
+fn copy_inputs(run_dir: &Path, inputs: &[&Path]) -> Result<Vec<String>> {
+    let mut copies = vec![];
+    for (i, input) in inputs.iter().enumerate() {
+        let name = match input.extension() {
+            Some(input_ext) => format!("input_{}.{}", i + 1, input_ext.to_string_lossy()),
+            None => format!("input_{}", i + 1),
+        };
+        std::fs::copy(input, run_dir.join(&name))?;
+        copies.push(name);
+    }
+    Ok(copies)
+}
+
+/// Shrinks an encoded image into the thumbnail of a run, returning its file name.
+///
+/// The history works without thumbnails, the annotated image is shown instead.
+fn thumbnail(run_dir: &Path, image: &[u8]) -> Option<String> {
+    image::load_from_memory(image).ok()
+        .map(|img| img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+        .and_then(|img| img.save(run_dir.join("thumbnail.png")).ok())
+        .map(|_| "thumbnail.png".to_owned())
+}
+
+/// Every run kept, newest first. Folders without a readable `run.json` are skipped.
+pub fn list() -> Result<Vec<Run>> {
+    let mut runs: Vec<Run> = std::fs::read_dir(dir()?)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("run.json")).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+
+    runs.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.id.cmp(&a.id)));
+    Ok(runs)
+}
+
+/// Deletes the folder of a run.
+pub fn remove(run: &Run) -> Result<()> {
+    std::fs::remove_dir_all(run.dir()?)?;
+    Ok(())
+}
+
+/// Creates a folder in `parent` named after the current time.
+///
+/// Returns the time in seconds since the Unix epoch, the name of the folder and its path.
+pub(super) fn timestamped_dir(parent: &Path) -> Result<(u64, String, PathBuf)> {
+    let created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let (date, time) = timestamp(created);
+    let (id, path) = unique_dir(parent, &format!("{date}_{time}"))?;
+    Ok((created, id, path))
+}
+
+/// Creates a folder named `name`, adding a counter if a run already took it.
+fn unique_dir(parent: &Path, name: &str) -> Result<(String, PathBuf)> {
+    let mut id = name.to_owned();
+    let mut n = 1;
+    loop {
+        let path = parent.join(&id);
+        match std::fs::create_dir(&path) {
+            Ok(()) => return Ok((id, path)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                n += 1;
+                id = format!("{name}_{n}");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Splits seconds since the Unix epoch into `YYYY-MM-DD` and `HH-MM-SS`, in UTC.
+fn timestamp(secs: u64) -> (String, String) {
+    let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{:02}-{:02}-{:02}", secs / 3_600, secs % 3_600 / 60, secs % 60),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned((date, time): (&str, &str)) -> (String, String) {
+        (date.to_owned(), time.to_owned())
+    }
+
+    #[test]
+    fn timestamps_in_utc() {
+        assert_eq!(timestamp(0), owned(("1970-01-01", "00-00-00")));
+        assert_eq!(timestamp(1_700_000_000), owned(("2023-11-14", "22-13-20")));
+        assert_eq!(timestamp(1_704_067_199), owned(("2023-12-31", "23-59-59")));
+        assert_eq!(timestamp(1_704_067_200), owned(("2024-01-01", "00-00-00")));
+    }
+
+    #[test]
+    fn civil_dates_of_leap_years() {
+        // 2000 is a leap year, 2100 isn't
+        assert_eq!(timestamp(951_782_400).0, "2000-02-29");
+        assert_eq!(timestamp(951_782_400 + 86_400).0, "2000-03-01");
+        assert_eq!(timestamp(4_107_456_000).0, "2100-02-28");
+        assert_eq!(timestamp(4_107_456_000 + 86_400).0, "2100-03-01");
+    }
+
+    #[test]
+    fn unique_dirs_get_a_counter() {
+        let parent = std::env::temp_dir().join(format!("imp-history-{}", std::process::id()));
+        std::fs::create_dir_all(&parent).unwrap();
+        let ids: Vec<_> = (0..3).map(|_| unique_dir(&parent, "run").map(|(id, path)| (id, path.is_dir()))).collect();
+        let missing = unique_dir(&parent.join("missing"), "run");
+        std::fs::remove_dir_all(&parent).unwrap();
+
+        let ids: Vec<_> = ids.into_iter().map(Result::unwrap).collect();
+        assert_eq!(ids, vec![("run".to_owned(), true), ("run_2".to_owned(), true), ("run_3".to_owned(), true)]);
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! Counting the cars that cross a line, like a gate in a traffic study.
+//!
+//! The line is crossed when the centre of a tracked car moves from one side of it to the
+//! other, between the ends of the line. Each car is counted once, on its first crossing,
+//! so boxes jittering over the line don't add up.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{track::Tracking, Detection};
+
+/// Segment drawn over the frames, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CountingLine {
+    pub start: (f32, f32),
+    pub end: (f32, f32),
+}
+
+impl CountingLine {
+    pub fn new(start: (f32, f32), end: (f32, f32)) -> Self {
+        CountingLine { start, end }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.start == self.end {
+            anyhow::bail!("The ends of the counting line must be different points");
+        }
+        Ok(())
+    }
+
+    /// Positive on the right of the line, seen on the screen going from its start to its end.
+    fn side(&self, (x, y): (f32, f32)) -> f32 {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        dx * (y - self.start.1) - dy * (x - self.start.0)
+    }
+
+    /// Whether the step from `a` to `b`, on opposite sides, goes through the segment.
+    fn between_ends(&self, a: (f32, f32), b: (f32, f32)) -> bool {
+        let (side_a, side_b) = (self.side(a), self.side(b));
+        let u = side_a / (side_a - side_b);
+        let point = (a.0 + u * (b.0 - a.0), a.1 + u * (b.1 - a.1));
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let t = ((point.0 - self.start.0) * dx + (point.1 - self.start.1) * dy) / (dx * dx + dy * dy);
+        (0. ..=1.).contains(&t)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// To the right of the line, seen on the screen going from its start to its end.
+    In,
+    /// To the left of the line.
+    Out,
+}
+
+/// A car crossing the line.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Crossing {
+    /// Id of the track of the car.
+    pub track: usize,
+    /// First frame the car was seen on the other side.
+    pub frame: usize,
+    pub direction: Direction,
+}
+
+/// Every car of `tracking` that crossed the line, in order.
+pub fn crossings(frames: &[Vec<Detection>], tracking: &Tracking, line: &CountingLine) -> Result<Vec<Crossing>> {
+    line.validate()?;
+
+    // Last centre of each track off the line, and the side it was on
+    let mut sides: Vec<Option<((f32, f32), f32)>> = vec![None; tracking.tracks.len()];
+    let mut crossed = vec![false; tracking.tracks.len()];
+    let mut crossings = vec![];
+
+    for (frame, (detections, ids)) in frames.iter().zip(&tracking.ids).enumerate() {
+        for (detection, &id) in detections.iter().zip(ids) {
+            if !tracking.confirmed(id) || crossed[id - 1] {
+                continue;
+            }
+            let centre = (detection.x as f32 + detection.w as f32 / 2., detection.y as f32 + detection.h as f32 / 2.);
+            let side = line.side(centre);
+            if side == 0. {
+                continue;
+            }
+
+            if let Some((previous, previous_side)) = sides[id - 1] {
+                if previous_side.signum() != side.signum() && line.between_ends(previous, centre) {
+                    let direction = if side > 0. { Direction::In } else { Direction::Out };
+                    crossings.push(Crossing { track: id, frame, direction });
+                    crossed[id - 1] = true;
+                }
+            }
+            sides[id - 1] = Some((centre, side));
+        }
+    }
+
+    Ok(crossings)
+}
+
+/// Crossings of a span of time of the video.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    /// Start and end of the span, in seconds since the start of the video.
+    pub start: f64,
+    pub end: f64,
+    #[serde(rename = "in")]
+    pub cars_in: usize,
+    #[serde(rename = "out")]
+    pub cars_out: usize,
+}
+
+/// Fails if spans of `seconds` can't group the crossings.
+pub fn check_bucket(seconds: f64) -> Result<()> {
+    if seconds.is_nan() || seconds <= 0. {
+        anyhow::bail!("The time buckets must be longer than 0 seconds");
+    }
+    Ok(())
+}
+
+/// Groups the crossings in spans of `seconds`, covering the `frames` of the video.
+pub fn buckets(crossings: &[Crossing], frames: usize, fps: f64, seconds: f64) -> Result<Vec<Bucket>> {
+    check_bucket(seconds)?;
+    let duration = frames as f64 / fps;
+    let amount = ((duration / seconds).ceil() as usize).max(1);
+
+    let mut buckets: Vec<Bucket> = (0..amount).map(|i| Bucket {
+        start: i as f64 * seconds,
+        end: ((i + 1) as f64 * seconds).min(duration),
+        cars_in: 0,
+        cars_out: 0,
+    }).collect();
+    for crossing in crossings {
+        let i = ((crossing.frame as f64 / fps / seconds) as usize).min(amount - 1);
+        match crossing.direction {
+            Direction::In => buckets[i].cars_in += 1,
+            Direction::Out => buckets[i].cars_out += 1,
+        }
+    }
+    Ok(buckets)
+}
+
+/// The buckets as CSV, with a `start,end,in,out` header.
+pub fn to_csv(buckets: &[Bucket]) -> String {
+    let mut csv = "start,end,in,out\n".to_owned();
+    for bucket in buckets {
+        csv.push_str(&format!("{:.3},{:.3},{},{}\n", bucket.start, bucket.end, bucket.cars_in, bucket.cars_out));
+    }
+    csv
+}
+
+/// Cars that went in and out through the line.
+pub fn totals(buckets: &[Bucket]) -> (usize, usize) {
+    buckets.iter().fold((0, 0), |(cars_in, cars_out), bucket| (cars_in + bucket.cars_in, cars_out + bucket.cars_out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::track::{track, TrackerParams};
+
+    /// Box of a car centred on the point.
+    fn car((x, y): (i32, i32)) -> Detection {
+        Detection::new("test", (x - 10, y - 5, 20, 10, 1.))
+    }
+
+    fn crossings_of(paths: &[Vec<(i32, i32)>], min_hits: usize) -> Vec<Crossing> {
+        let frames: Vec<Vec<Detection>> = paths.iter().map(|frame| frame.iter().copied().map(car).collect()).collect();
+        let tracking = track(&frames, &TrackerParams { min_hits, ..TrackerParams::default() }).unwrap();
+        crossings(&frames, &tracking, &CountingLine::new((0., 50.), (100., 50.))).unwrap()
+    }
+
+    #[test]
+    fn crossing_direction() {
+        // One car goes down through the line while another goes up
+        let paths = vec![
+            vec![(50, 30), (80, 70)],
+            vec![(50, 45), (80, 55)],
+            vec![(50, 55), (80, 45)],
+            vec![(50, 70), (80, 30)],
+        ];
+        assert_eq!(crossings_of(&paths, 3), vec![
+            Crossing { track: 1, frame: 2, direction: Direction::In },
+            Crossing { track: 2, frame: 2, direction: Direction::Out },
+        ]);
+    }
+
+    #[test]
+    fn counts_each_car_once() {
+        let paths: Vec<_> = [45, 55, 45, 55, 45].iter().map(|&y| vec![(50, y)]).collect();
+        assert_eq!(crossings_of(&paths, 3), vec![Crossing { track: 1, frame: 1, direction: Direction::In }]);
+    }
+
+    #[test]
+    fn ignores_cars_off_the_segment_and_noise() {
+        let beyond: Vec<_> = [30, 45, 55, 70].iter().map(|&y| vec![(150, y)]).collect();
+        assert!(crossings_of(&beyond, 3).is_empty());
+
+        let short: Vec<_> = [45, 55].iter().map(|&y| vec![(50, y)]).collect();
+        assert!(crossings_of(&short, 3).is_empty());
+        assert_eq!(crossings_of(&short, 2).len(), 1);
+    }
+
+    #[test]
+    fn rejects_a_point() {
+        let tracking = track(&[], &TrackerParams::default()).unwrap();
+        assert!(crossings(&[], &tracking, &CountingLine::new((5., 5.), (5., 5.))).is_err());
+    }
+
+    #[test]
+    fn time_buckets() {
+        let crossing = |frame, direction| Crossing { track: 1, frame, direction };
+        let all = [
+            crossing(5, Direction::In),
+            crossing(25, Direction::Out),
+            crossing(35, Direction::In),
+            crossing(40, Direction::Out),
+        ];
+        // 4 seconds at 10 fps, the last bucket is cut at the end of the video
+        let grouped = buckets(&all, 40, 10., 3.).unwrap();
+        assert_eq!(grouped, vec![
+            Bucket { start: 0., end: 3., cars_in: 1, cars_out: 1 },
+            Bucket { start: 3., end: 4., cars_in: 1, cars_out: 1 },
+        ]);
+        assert_eq!(to_csv(&grouped), "start,end,in,out\n0.000,3.000,1,1\n3.000,4.000,1,1\n");
+        assert_eq!(totals(&grouped), (2, 2));
+
+        assert_eq!(buckets(&[], 0, 10., 3.).unwrap(), vec![Bucket { start: 0., end: 0., cars_in: 0, cars_out: 0 }]);
+        assert!(buckets(&all, 40, 10., 0.).is_err());
+        assert!(buckets(&all, 40, 10., f64::NAN).is_err());
+    }
+}
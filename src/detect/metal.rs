@@ -0,0 +1,309 @@
+//! Native implementation of `haar.py` and `diffcon.py` through the `opencv` crate.
+
+use std::path::Path;
+
+use anyhow::Result;
+use opencv::{
+    core::{self, Mat, Point, Rect, Scalar, Size, Vector},
+    imgcodecs, imgproc, objdetect,
+    prelude::*,
+    video,
+    videoio::{self, VideoCapture, VideoWriter},
+};
+
+use super::{cache::FileCache, doctor::Check, error::check_sizes, load, roi::RegionKind, DiffParams, Detection, DetectError, HaarParams, Outcome, Region, VideoOutcome};
+
+/// Cascades already loaded, reused by later detections.
+static CASCADES: FileCache<objdetect::CascadeClassifier> = FileCache::new();
+
+/// Decodes the file contents, `imread` only takes UTF-8 paths.
+fn read(img: &Path) -> Result<Mat> {
+    let mat = imgcodecs::imdecode(&Vector::<u8>::from_slice(&load(img)?), imgcodecs::IMREAD_COLOR)?;
+    if mat.empty() {
+        return Err(DetectError::UnreadableImage(img.display().to_string()).into());
+    }
+    Ok(mat)
+}
+
+fn encode(mat: &Mat, ext: &str) -> Result<Vec<u8>> {
+    let mut buf = Vector::<u8>::new();
+    if !imgcodecs::imencode(&format!(".{ext}"), mat, &mut buf, &Vector::new())? {
+        return Err(DetectError::EncodeFailure(ext.to_owned()).into());
+    }
+    Ok(buf.to_vec())
+}
+
+fn gray_blur(img: &Mat, ksize: i32) -> Result<Mat> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color(img, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+    let mut blur = Mat::default();
+    imgproc::gaussian_blur(&gray, &mut blur, Size::new(ksize, ksize), 0., 0., core::BORDER_DEFAULT)?;
+    Ok(blur)
+}
+
+/// Copy of `img` black outside the regions, `None` without regions.
+fn masked(img: &Mat, regions: &[Region]) -> Result<Option<Mat>> {
+    if regions.is_empty() {
+        return Ok(None);
+    }
+
+    // Without include regions the whole image is included. Each polygon is filled on its own,
+    // `fill_poly` would leave holes where the polygons of a single call overlap
+    let includes = regions.iter().any(|region| region.kind == RegionKind::Include);
+    let start = if includes { 0. } else { 255. };
+    let mut mask = Mat::new_rows_cols_with_default(img.rows(), img.cols(), core::CV_8UC1, Scalar::all(start))?;
+    let ordered = regions.iter().filter(|region| region.kind == RegionKind::Include)
+        .chain(regions.iter().filter(|region| region.kind == RegionKind::Exclude));
+    for region in ordered {
+        let corners: Vector<Point> = region.pixels().into_iter().map(|(x, y)| Point::new(x, y)).collect();
+        let polygons = Vector::<Vector<Point>>::from_iter([corners]);
+        let value = if region.kind == RegionKind::Include { 255. } else { 0. };
+        imgproc::fill_poly(&mut mask, &polygons, Scalar::all(value), imgproc::LINE_8, 0, Point::new(0, 0))?;
+    }
+
+    let mut masked = Mat::default();
+    core::bitwise_and(img, img, &mut masked, &mask)?;
+    Ok(Some(masked))
+}
+
+pub fn diff_n_conn(img1: &Path, img2: &Path, ext: &str, params: &DiffParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    let (mut img1, img2) = (read(img1)?, read(img2)?);
+    let detections = find_changes(&mut img1, &img2, params, regions)?;
+    Ok(Outcome { detections, image: encode(&img1, ext)? })
+}
+
+/// Draws what changed between the images on `img1`, returns the boxes.
+fn find_changes(img1: &mut Mat, img2: &Mat, params: &DiffParams, regions: &[Region]) -> Result<Vec<Detection>> {
+    check_sizes((img1.cols() as u32, img1.rows() as u32), (img2.cols() as u32, img2.rows() as u32))?;
+
+    // Working copies with the longest side of `work_size` pixels, keeping the aspect ratio
+    let (width, height) = (img1.cols(), img1.rows());
+    let scale = params.work_size as f64 / width.max(height) as f64;
+    let size = Size::new(
+        ((width as f64 * scale).round() as i32).max(1),
+        ((height as f64 * scale).round() as i32).max(1),
+    );
+    let (masked1, masked2) = (masked(img1, regions)?, masked(img2, regions)?);
+    let mut img1_small = Mat::default();
+    let mut img2_small = Mat::default();
+    imgproc::resize(masked1.as_ref().unwrap_or(img1), &mut img1_small, size, 0., 0., imgproc::INTER_LINEAR)?;
+    imgproc::resize(masked2.as_ref().unwrap_or(img2), &mut img2_small, size, 0., 0., imgproc::INTER_LINEAR)?;
+
+    // Convert to grayscale and apply Gaussian blur
+    let img1_blur = gray_blur(&img1_small, params.blur)?;
+    let img2_blur = gray_blur(&img2_small, params.blur)?;
+
+    // Binary thresholding of the images
+    let threshold = params.threshold as f64;
+    let mut img1_thresh = Mat::default();
+    let mut img2_thresh = Mat::default();
+    imgproc::threshold(&img1_blur, &mut img1_thresh, threshold, 255., imgproc::THRESH_BINARY)?;
+    imgproc::threshold(&img2_blur, &mut img2_thresh, threshold, 255., imgproc::THRESH_BINARY)?;
+
+    // Get difference between images
+    let mut img_diff = Mat::default();
+    core::absdiff(&img1_thresh, &img2_thresh, &mut img_diff)?;
+
+    // Reduce noise
+    let kernel = Mat::ones(params.kernel.0, params.kernel.1, core::CV_8U)?.to_mat()?;
+    let mut opened = Mat::default();
+    imgproc::morphology_ex(
+        &img_diff, &mut opened, imgproc::MORPH_OPEN, &kernel,
+        Point::new(-1, -1), 1, core::BORDER_CONSTANT, imgproc::morphology_default_border_value()?,
+    )?;
+
+    // Dilate the image
+    let mut dilated = Mat::default();
+    imgproc::dilate(
+        &opened, &mut dilated, &kernel,
+        Point::new(-1, -1), params.dilate_iterations, core::BORDER_CONSTANT, imgproc::morphology_default_border_value()?,
+    )?;
+
+    // Show connected components in the image
+    let mut labels = Mat::default();
+    let mut stats = Mat::default();
+    let mut centroids = Mat::default();
+    let num_labels = imgproc::connected_components_with_stats(
+        &dilated, &mut labels, &mut stats, &mut centroids, 8, core::CV_32S,
+    )?;
+
+    // Draw the bounding boxes of the components on the original image, label 0 is the background
+    let thickness = ((2. / scale).round() as i32).max(2);
+    let mut detections = vec![];
+    for i in 1..num_labels {
+        let x = *stats.at_2d::<i32>(i, imgproc::CC_STAT_LEFT)?;
+        let y = *stats.at_2d::<i32>(i, imgproc::CC_STAT_TOP)?;
+        let w = *stats.at_2d::<i32>(i, imgproc::CC_STAT_WIDTH)?;
+        let h = *stats.at_2d::<i32>(i, imgproc::CC_STAT_HEIGHT)?;
+        let area = *stats.at_2d::<i32>(i, imgproc::CC_STAT_AREA)?;
+
+        let (x0, y0) = ((x as f64 / scale) as i32, (y as f64 / scale) as i32);
+        let x1 = (((x + w) as f64 / scale).ceil() as i32).min(width);
+        let y1 = (((y + h) as f64 / scale).ceil() as i32).min(height);
+        let rect = Rect::new(x0, y0, x1 - x0, y1 - y0);
+
+        detections.push(Detection::new("diff", (rect.x, rect.y, rect.width, rect.height, area as f32 / (w * h) as f32)));
+        imgproc::rectangle(img1, rect, Scalar::new(0., 255., 0., 0.), thickness, imgproc::LINE_8, 0)?;
+    }
+
+    Ok(detections)
+}
+
+pub fn haar_cascade(img: &Path, ext: &str, xml: &Path, params: &HaarParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    let mut img = read(img)?;
+    let detections = CASCADES.with(xml, load_cascade, |car_cascade| find_cars(&mut img, car_cascade, params, regions))??;
+    Ok(Outcome { detections, image: encode(&img, ext)? })
+}
+
+/// Draws the cars found on `img`, returns their boxes.
+fn find_cars(img: &mut Mat, car_cascade: &mut objdetect::CascadeClassifier, params: &HaarParams, regions: &[Region]) -> Result<Vec<Detection>> {
+    let blur = gray_blur(masked(img, regions)?.as_ref().unwrap_or(img), params.blur)?;
+    let mut dilated = Mat::default();
+    let kernel = Mat::ones(params.dilate, params.dilate, core::CV_8U)?.to_mat()?;
+    imgproc::dilate(
+        &blur, &mut dilated, &kernel,
+        Point::new(-1, -1), 1, core::BORDER_CONSTANT, imgproc::morphology_default_border_value()?,
+    )?;
+
+    let kernel = imgproc::get_structuring_element(imgproc::MORPH_ELLIPSE, Size::new(params.close, params.close), Point::new(-1, -1))?;
+    let mut closing = Mat::default();
+    imgproc::morphology_ex(
+        &dilated, &mut closing, imgproc::MORPH_CLOSE, &kernel,
+        Point::new(-1, -1), 1, core::BORDER_CONSTANT, imgproc::morphology_default_border_value()?,
+    )?;
+
+    let mut cars = Vector::<Rect>::new();
+    let mut neighbours = Vector::<i32>::new();
+    car_cascade.detect_multi_scale2(
+        &closing, &mut cars, &mut neighbours, params.scale_factor, params.min_neighbors, 0,
+        Size::new(params.min_size.0, params.min_size.1), Size::new(params.max_size.0, params.max_size.1),
+    )?;
+
+    let mut detections = vec![];
+    for (rect, n) in cars.iter().zip(neighbours.iter()) {
+        imgproc::rectangle(img, rect, Scalar::new(255., 0., 0., 0.), 2, imgproc::LINE_8, 0)?;
+        detections.push(Detection::new("haar", (rect.x, rect.y, rect.width, rect.height, n as f32)));
+    }
+
+    Ok(detections)
+}
+
+/// Calls `f` on every frame of `video`, writing the frames it annotates to `output`.
+fn each_frame(video: &Path, output: &Path, fourcc: &str, mut f: impl FnMut(&mut Mat) -> Result<Vec<Detection>>) -> Result<VideoOutcome> {
+    // `VideoCapture` and `VideoWriter` only take UTF-8 paths
+    let video_str = video.to_str()
+        .ok_or_else(|| DetectError::UnreadableVideo(format!("{} (the path is not valid UTF-8)", video.display())))?;
+    let output_str = output.to_str()
+        .ok_or_else(|| DetectError::WriteFailure(format!("{} (the path is not valid UTF-8)", output.display())))?;
+
+    let mut capture = VideoCapture::from_file(video_str, videoio::CAP_ANY)?;
+    if !capture.is_opened()? {
+        return Err(DetectError::UnreadableVideo(video.display().to_string()).into());
+    }
+    // Some containers don't store the frame rate
+    let fps = match capture.get(videoio::CAP_PROP_FPS)? {
+        fps if fps > 0. => fps,
+        _ => 25.,
+    };
+
+    let codec: Vec<char> = fourcc.chars().collect();
+    let mut writer: Option<VideoWriter> = None;
+    let mut frames = vec![];
+    let mut frame = Mat::default();
+    while capture.read(&mut frame)? {
+        if frame.empty() {
+            break;
+        }
+        let writer = match &mut writer {
+            Some(writer) => writer,
+            None => {
+                let size = Size::new(frame.cols(), frame.rows());
+                let fourcc = VideoWriter::fourcc(codec[0], codec[1], codec[2], codec[3])?;
+                let opened = VideoWriter::new(output_str, fourcc, fps, size, true)?;
+                if !opened.is_opened()? {
+                    return Err(DetectError::WriteFailure(output.display().to_string()).into());
+                }
+                writer.insert(opened)
+            }
+        };
+        frames.push(f(&mut frame)?);
+        writer.write(&frame)?;
+    }
+
+    if frames.is_empty() {
+        return Err(DetectError::UnreadableVideo(video.display().to_string()).into());
+    }
+    Ok(VideoOutcome::new(frames, fps, output))
+}
+
+pub fn first_frame(video: &Path) -> Result<Vec<u8>> {
+    let video_str = video.to_str()
+        .ok_or_else(|| DetectError::UnreadableVideo(format!("{} (the path is not valid UTF-8)", video.display())))?;
+    let mut capture = VideoCapture::from_file(video_str, videoio::CAP_ANY)?;
+    let mut frame = Mat::default();
+    if !capture.is_opened()? || !capture.read(&mut frame)? || frame.empty() {
+        return Err(DetectError::UnreadableVideo(video.display().to_string()).into());
+    }
+    encode(&frame, "png")
+}
+
+pub fn haar_video(video: &Path, output: &Path, fourcc: &str, xml: &Path, params: &HaarParams, regions: &[Region]) -> Result<VideoOutcome> {
+    params.validate()?;
+    CASCADES.with(xml, load_cascade, |car_cascade| {
+        each_frame(video, output, fourcc, |frame| find_cars(frame, car_cascade, params, regions))
+    })?
+}
+
+pub fn diff_video(video: &Path, reference: Option<&Path>, output: &Path, fourcc: &str, params: &DiffParams, regions: &[Region]) -> Result<VideoOutcome> {
+    params.validate()?;
+    // Without a reference every frame is compared with the first one
+    let mut reference = reference.map(read).transpose()?;
+    each_frame(video, output, fourcc, |frame| {
+        let reference = match &reference {
+            Some(reference) => reference,
+            None => reference.insert(frame.try_clone()?),
+        };
+        find_changes(frame, reference, params, regions)
+    })
+}
+
+/// Background of the frames, as PNG, learned by OpenCV's MOG2 subtractor.
+pub fn mog2_background(frames: &[&Path]) -> Result<Vec<u8>> {
+    let mut subtractor = video::create_background_subtractor_mog2(frames.len() as i32, 16., false)?;
+    let mut mask = Mat::default();
+    let mut size = None;
+    for frame in frames {
+        let img = read(frame)?;
+        let frame_size = (img.cols() as u32, img.rows() as u32);
+        match size {
+            Some(size) => check_sizes(size, frame_size)?,
+            None => size = Some(frame_size),
+        }
+        subtractor.apply(&img, &mut mask, -1.)?;
+    }
+
+    let mut background = Mat::default();
+    subtractor.get_background_image(&mut background)?;
+    encode(&background, "png")
+}
+
+fn load_cascade(xml: &Path) -> Result<objdetect::CascadeClassifier> {
+    let xml_str = xml.to_str()
+        .ok_or_else(|| DetectError::MissingCascade(format!("{} (the path is not valid UTF-8)", xml.display())))?;
+    let car_cascade = objdetect::CascadeClassifier::new(xml_str)?;
+    if car_cascade.empty()? {
+        return Err(DetectError::MissingCascade(xml.display().to_string()).into());
+    }
+    Ok(car_cascade)
+}
+
+/// Reports the version of OpenCV linked in.
+pub fn diagnose() -> Vec<Check> {
+    vec![Check::new(
+        "OpenCV",
+        core::get_version_string().map_err(|err| err.to_string()),
+        "Install OpenCV 4 and rebuild with the `opencv-metal` feature",
+    )]
+}
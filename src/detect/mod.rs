@@ -0,0 +1,460 @@
+//! Car counting methods.
+//!
+//! Each method has one implementation per [`Backend`], enabled by its feature:
+//!
+//! - `opencv-python` runs the embedded `haar.py`/`diffcon.py` scripts through pyo3.
+//! - `opencv-metal` runs the same pipelines natively through the `opencv` crate.
+//! - `native` runs them in pure Rust, without Python or OpenCV.
+//!
+//! Every enabled backend can be picked at runtime, [`Backend::default`] prefers OpenCV.
+//!
+//! Methods are exposed through the [`Detector`] trait and enumerated by [`registry`],
+//! so the UI, the CLI and batch jobs can run any of them the same way.
+
+use std::{path::{Path, PathBuf}, sync::Arc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub mod background;
+pub mod batch;
+#[cfg(any(feature = "opencv-metal", feature = "native"))]
+mod cache;
+pub mod cascades;
+pub mod doctor;
+mod error;
+pub mod export;
+#[cfg(test)]
+mod fixtures;
+pub mod history;
+pub mod line;
+mod params;
+pub mod roi;
+pub mod track;
+pub mod video;
+
+pub use error::DetectError;
+pub use params::{DiffParams, HaarParams};
+pub use roi::Region;
+pub use video::VideoOutcome;
+
+#[cfg(feature = "opencv-metal")]
+mod metal;
+#[cfg(feature = "native")]
+mod native;
+#[cfg(feature = "opencv-python")]
+mod python;
+
+#[cfg(not(any(feature = "opencv-python", feature = "opencv-metal", feature = "native")))]
+compile_error!("Enable at least one of the `opencv-python`, `opencv-metal` or `native` features");
+
+/// Implementation the methods run on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// OpenCV through the `opencv` crate.
+    #[cfg(feature = "opencv-metal")]
+    Metal,
+    /// The embedded Python scripts, needs Python with OpenCV installed.
+    #[cfg(feature = "opencv-python")]
+    Python,
+    /// Pure Rust.
+    #[cfg(feature = "native")]
+    Native,
+}
+
+impl Backend {
+    /// Every backend enabled in this build, by order of preference.
+    pub const ALL: &'static [Backend] = &[
+        #[cfg(feature = "opencv-metal")]
+        Backend::Metal,
+        #[cfg(feature = "opencv-python")]
+        Backend::Python,
+        #[cfg(feature = "native")]
+        Backend::Native,
+    ];
+
+    /// Short unique identifier, used by the CLI.
+    pub fn id(self) -> &'static str {
+        match self {
+            #[cfg(feature = "opencv-metal")]
+            Backend::Metal => "metal",
+            #[cfg(feature = "opencv-python")]
+            Backend::Python => "python",
+            #[cfg(feature = "native")]
+            Backend::Native => "native",
+        }
+    }
+
+    /// Name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "opencv-metal")]
+            Backend::Metal => "OpenCV",
+            #[cfg(feature = "opencv-python")]
+            Backend::Python => "OpenCV (Python)",
+            #[cfg(feature = "native")]
+            Backend::Native => "Native",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Backend::ALL.iter().copied().find(|backend| backend.id() == id)
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::ALL[0]
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Prepares the enabled backends, must be called once before any detection.
+pub fn init() {
+    #[cfg(feature = "opencv-python")]
+    pyo3::prepare_freethreaded_python();
+}
+
+/// Reads the encoded contents of an input image.
+fn load(img: &Path) -> Result<Vec<u8>, DetectError> {
+    std::fs::read(img).map_err(|err| DetectError::UnreadableImage(format!("{} ({err})", img.display())))
+}
+
+/// Returns the data directory of the application, creating it if needed.
+pub fn data_dir() -> Result<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "up", "imp")
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the data directory"))?;
+    let data_dir = dirs.data_dir();
+
+    if !data_dir.exists() {
+        std::fs::create_dir_all(data_dir)?;
+    }
+
+    Ok(data_dir.to_path_buf())
+}
+
+/// A car found by a [`Detector`], in pixel coordinates of the annotated image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Detection {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    /// Confidence of the detection, its meaning depends on the method.
+    ///
+    /// For Haar Cascade it's the amount of neighbouring windows merged into the box,
+    /// for Diff & Connect it's the fraction of the box covered by the component.
+    pub score: f32,
+    /// Id of the [`Detector`] that found it.
+    pub method: String,
+}
+
+impl Detection {
+    pub fn new(method: &str, (x, y, w, h, score): (i32, i32, i32, i32, f32)) -> Self {
+        Detection { x, y, w, h, score, method: method.to_owned() }
+    }
+}
+
+/// What a [`Detector`] found.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub detections: Vec<Detection>,
+    /// The input image with the detections drawn on top, encoded in the requested format.
+    pub image: Vec<u8>,
+}
+
+impl Outcome {
+    /// Amount of cars found.
+    pub fn count(&self) -> usize {
+        self.detections.len()
+    }
+}
+
+/// The number of images a [`Detector`] works on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Counts the cars in one image.
+    Single,
+    /// Counts the cars that changed between two images of the same place.
+    Pair,
+}
+
+impl Kind {
+    /// Number of input images.
+    pub fn inputs(self) -> usize {
+        match self {
+            Kind::Single => 1,
+            Kind::Pair => 2,
+        }
+    }
+}
+
+/// Images to run a [`Detector`] on.
+#[derive(Clone, Copy, Debug)]
+pub enum Input<'a> {
+    Single(&'a Path),
+    Pair(&'a Path, &'a Path),
+}
+
+impl<'a> Input<'a> {
+    /// Builds the input of the given kind from a list of paths, `None` if the amount doesn't match.
+    pub fn new(kind: Kind, paths: &[&'a Path]) -> Option<Self> {
+        match (kind, paths) {
+            (Kind::Single, [img]) => Some(Input::Single(img)),
+            (Kind::Pair, [img1, img2]) => Some(Input::Pair(img1, img2)),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self {
+            Input::Single(_) => Kind::Single,
+            Input::Pair(_, _) => Kind::Pair,
+        }
+    }
+
+    /// The images, in order.
+    pub fn paths(&self) -> Vec<&'a Path> {
+        match *self {
+            Input::Single(img) => vec![img],
+            Input::Pair(img1, img2) => vec![img1, img2],
+        }
+    }
+}
+
+/// A car counting method.
+pub trait Detector: Send + Sync {
+    /// Short unique identifier, used by the CLI.
+    fn id(&self) -> &'static str;
+
+    /// Name shown to the user.
+    fn name(&self) -> &'static str;
+
+    fn kind(&self) -> Kind;
+
+    /// Implementation the method runs on.
+    fn backend(&self) -> Backend;
+
+    /// Parameters of the method, kept in the [`history`] of runs.
+    fn params(&self) -> serde_json::Value;
+
+    /// Runs the method, returning every car found and the annotated image encoded as `ext`.
+    fn detect(&self, input: Input, ext: &str) -> Result<Outcome, DetectError>;
+
+    /// Runs the method on every frame of `video`, writing the annotated video to `output`.
+    ///
+    /// Pair methods compare each frame with `reference`, or with the first frame when it's `None`.
+    fn detect_video(&self, video: &Path, reference: Option<&Path>, output: &Path) -> Result<VideoOutcome, DetectError>;
+
+    /// The same method, restricted to the [`roi`] regions.
+    fn restricted_to(&self, regions: Vec<Region>) -> Arc<dyn Detector>;
+}
+
+/// Haar cascade classifier, trained on cars unless another model of the [`cascades`] library is used.
+#[derive(Clone, Debug, Default)]
+pub struct HaarCascade {
+    pub params: HaarParams,
+    /// Path of the cascade XML, `None` uses the embedded `cars.xml`.
+    pub cascade: Option<PathBuf>,
+    pub backend: Backend,
+    /// Regions of interest, empty to look at the whole image.
+    pub regions: Vec<Region>,
+}
+
+impl HaarCascade {
+    pub fn new(params: HaarParams) -> Self {
+        HaarCascade { params, cascade: None, backend: Backend::default(), regions: vec![] }
+    }
+
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_cascade(mut self, cascade: PathBuf) -> Self {
+        self.cascade = Some(cascade);
+        self
+    }
+
+    pub fn with_regions(mut self, regions: Vec<Region>) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Path of the cascade XML to use, failing if the file is missing.
+    fn xml(&self) -> Result<PathBuf, DetectError> {
+        let xml = match &self.cascade {
+            Some(cascade) => cascade.clone(),
+            None => cascades::builtin_path()?,
+        };
+        if !xml.is_file() {
+            return Err(DetectError::MissingCascade(xml.display().to_string()));
+        }
+        Ok(xml)
+    }
+}
+
+impl Detector for HaarCascade {
+    fn id(&self) -> &'static str {
+        "haar"
+    }
+
+    fn name(&self) -> &'static str {
+        "Haar Cascade"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::Single
+    }
+
+    fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "params": self.params,
+            "cascade": self.cascade,
+            "regions": self.regions,
+        })
+    }
+
+    fn detect(&self, input: Input, ext: &str) -> Result<Outcome, DetectError> {
+        error::check_format(ext)?;
+        match input {
+            Input::Single(img) => {
+                roi::validate(&self.regions)?;
+                let xml = self.xml()?;
+                let outcome = match self.backend {
+                    #[cfg(feature = "opencv-metal")]
+                    Backend::Metal => metal::haar_cascade(img, ext, &xml, &self.params, &self.regions),
+                    #[cfg(feature = "opencv-python")]
+                    Backend::Python => python::haar_cascade(img, ext, &xml, &self.params, &self.regions),
+                    #[cfg(feature = "native")]
+                    Backend::Native => native::haar_cascade(img, ext, &xml, &self.params, &self.regions),
+                };
+                Ok(outcome?)
+            }
+            _ => Err(anyhow::anyhow!("{} expects a single image", self.name()).into()),
+        }
+    }
+
+    fn detect_video(&self, video: &Path, _reference: Option<&Path>, output: &Path) -> Result<VideoOutcome, DetectError> {
+        let fourcc = video::fourcc(output)?;
+        roi::validate(&self.regions)?;
+        let xml = self.xml()?;
+        let outcome = match self.backend {
+            #[cfg(feature = "opencv-metal")]
+            Backend::Metal => metal::haar_video(video, output, fourcc, &xml, &self.params, &self.regions),
+            #[cfg(feature = "opencv-python")]
+            Backend::Python => python::haar_video(video, output, fourcc, &xml, &self.params, &self.regions),
+            #[cfg(feature = "native")]
+            Backend::Native => native::haar_video(video, output, fourcc, &xml, &self.params, &self.regions),
+        };
+        Ok(outcome?)
+    }
+
+    fn restricted_to(&self, regions: Vec<Region>) -> Arc<dyn Detector> {
+        Arc::new(self.clone().with_regions(regions))
+    }
+}
+
+/// Difference between two images followed by connected components.
+#[derive(Clone, Debug, Default)]
+pub struct DiffConnect {
+    pub params: DiffParams,
+    pub backend: Backend,
+    /// Regions of interest, empty to compare the whole images.
+    pub regions: Vec<Region>,
+}
+
+impl DiffConnect {
+    pub fn new(params: DiffParams) -> Self {
+        DiffConnect { params, backend: Backend::default(), regions: vec![] }
+    }
+
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_regions(mut self, regions: Vec<Region>) -> Self {
+        self.regions = regions;
+        self
+    }
+}
+
+impl Detector for DiffConnect {
+    fn id(&self) -> &'static str {
+        "diff"
+    }
+
+    fn name(&self) -> &'static str {
+        "Diff & Connect"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::Pair
+    }
+
+    fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::json!({ "params": self.params, "regions": self.regions })
+    }
+
+    fn detect(&self, input: Input, ext: &str) -> Result<Outcome, DetectError> {
+        error::check_format(ext)?;
+        roi::validate(&self.regions)?;
+        let outcome = match input {
+            Input::Pair(img1, img2) => match self.backend {
+                #[cfg(feature = "opencv-metal")]
+                Backend::Metal => metal::diff_n_conn(img1, img2, ext, &self.params, &self.regions),
+                #[cfg(feature = "opencv-python")]
+                Backend::Python => python::diff_n_conn(img1, img2, ext, &self.params, &self.regions),
+                #[cfg(feature = "native")]
+                Backend::Native => native::diff_n_conn(img1, img2, ext, &self.params, &self.regions),
+            },
+            _ => return Err(anyhow::anyhow!("{} expects a pair of images", self.name()).into()),
+        };
+        Ok(outcome?)
+    }
+
+    fn detect_video(&self, video: &Path, reference: Option<&Path>, output: &Path) -> Result<VideoOutcome, DetectError> {
+        let fourcc = video::fourcc(output)?;
+        roi::validate(&self.regions)?;
+        let outcome = match self.backend {
+            #[cfg(feature = "opencv-metal")]
+            Backend::Metal => metal::diff_video(video, reference, output, fourcc, &self.params, &self.regions),
+            #[cfg(feature = "opencv-python")]
+            Backend::Python => python::diff_video(video, reference, output, fourcc, &self.params, &self.regions),
+            #[cfg(feature = "native")]
+            Backend::Native => native::diff_video(video, reference, output, fourcc, &self.params, &self.regions),
+        };
+        Ok(outcome?)
+    }
+
+    fn restricted_to(&self, regions: Vec<Region>) -> Arc<dyn Detector> {
+        Arc::new(self.clone().with_regions(regions))
+    }
+}
+
+/// Every available method, running on the given backend.
+pub fn registry(backend: Backend) -> Vec<Arc<dyn Detector>> {
+    vec![
+        Arc::new(DiffConnect::default().with_backend(backend)),
+        Arc::new(HaarCascade::default().with_backend(backend)),
+    ]
+}
+
+/// Finds a method of the [`registry`] by its id.
+pub fn find(id: &str, backend: Backend) -> Option<Arc<dyn Detector>> {
+    registry(backend).into_iter().find(|detector| detector.id() == id)
+}
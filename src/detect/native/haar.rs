@@ -0,0 +1,683 @@
+//! Haar cascade classifier in pure Rust, reading the old `opencv-haar-classifier` XML format.
+//!
+//! Evaluation follows `cv::CascadeClassifier::detectMultiScale`: the image is shrunk by the
+//! scale factor until it's smaller than the detection window, every window position is run
+//! through the stages using integral images, and the windows found are grouped like
+//! `cv::groupRectangles`.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::GrayImage;
+use roxmltree::Node;
+
+use super::{imgproc, DetectError};
+
+/// Subtracted from every stage threshold, same as OpenCV does when loading a cascade.
+const THRESHOLD_EPS: f32 = 1e-5;
+
+/// Relative distance under which two windows belong to the same object.
+const GROUP_EPS: f64 = 0.2;
+
+#[derive(Clone, Debug)]
+struct Rect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    weight: f32,
+}
+
+#[derive(Clone, Debug)]
+struct Feature {
+    rects: Vec<Rect>,
+    /// Rotated 45 degrees, the rectangle grows down-right along `w` and down-left along `h`.
+    tilted: bool,
+}
+
+/// Where a node goes after comparing its feature with the threshold.
+#[derive(Clone, Copy, Debug)]
+enum Branch {
+    Leaf(f32),
+    Node(usize),
+}
+
+#[derive(Clone, Debug)]
+struct TreeNode {
+    feature: Feature,
+    threshold: f32,
+    left: Branch,
+    right: Branch,
+}
+
+#[derive(Clone, Debug)]
+struct Stage {
+    /// Each tree is a list of nodes, starting at the root.
+    trees: Vec<Vec<TreeNode>>,
+    threshold: f32,
+}
+
+/// A cascade loaded from an XML file.
+#[derive(Clone, Debug)]
+pub struct Cascade {
+    /// Size of the detection window, as `(width, height)`.
+    size: (i32, i32),
+    stages: Vec<Stage>,
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .ok_or_else(|| anyhow::anyhow!("Missing <{name}> in <{}>", node.tag_name().name()))
+}
+
+fn items<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(|n| n.has_tag_name("_"))
+}
+
+fn text<'a>(node: Node<'a, '_>) -> &'a str {
+    node.text().unwrap_or_default().trim()
+}
+
+fn number<T: std::str::FromStr>(node: Node, name: &str) -> Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = text(child(node, name)?);
+    value.parse().with_context(|| format!("Invalid <{name}> value {value:?}"))
+}
+
+fn branch(node: Node, leaf: &str, next: &str) -> Result<Branch> {
+    if node.children().any(|n| n.has_tag_name(leaf)) {
+        Ok(Branch::Leaf(number(node, leaf)?))
+    } else {
+        Ok(Branch::Node(number(node, next)?))
+    }
+}
+
+fn feature(node: Node) -> Result<Feature> {
+    let rects = items(child(node, "rects")?)
+        .map(|rect| {
+            let values: Vec<&str> = text(rect).split_whitespace().collect();
+            match values[..] {
+                [x, y, w, h, weight] => Ok(Rect {
+                    x: x.parse()?,
+                    y: y.parse()?,
+                    w: w.parse()?,
+                    h: h.parse()?,
+                    weight: weight.parse()?,
+                }),
+                _ => anyhow::bail!("Invalid feature rectangle {:?}", text(rect)),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let tilted = match node.children().find(|n| n.has_tag_name("tilted")) {
+        Some(tilted) => text(tilted) != "0",
+        None => false,
+    };
+
+    Ok(Feature { rects, tilted })
+}
+
+impl Rect {
+    /// Whether every corner the evaluator reads is inside a window of `size`.
+    fn fits(&self, size: (i32, i32), tilted: bool) -> bool {
+        let (left, right, bottom) = if tilted {
+            (self.x - self.h, self.x + self.w, self.y + self.w + self.h)
+        } else {
+            (self.x, self.x + self.w, self.y + self.h)
+        };
+        self.w >= 0 && self.h >= 0 && left >= 0 && self.y >= 0 && right <= size.0 && bottom <= size.1
+    }
+}
+
+impl Cascade {
+    pub fn load(path: &Path) -> Result<Self, DetectError> {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))
+            .and_then(|xml| Cascade::parse(&xml))
+            .map_err(|err| DetectError::MissingCascade(format!("{} ({err:#})", path.display())))
+    }
+
+    pub fn parse(xml: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("opencv_storage") {
+            anyhow::bail!("Not an OpenCV file");
+        }
+
+        let classifier = root.children()
+            .find(|n| n.is_element())
+            .ok_or_else(|| anyhow::anyhow!("The file is empty"))?;
+        if classifier.attribute("type_id") != Some("opencv-haar-classifier") {
+            anyhow::bail!("Only cascades in the old opencv-haar-classifier format are supported");
+        }
+
+        let size: Vec<i32> = text(child(classifier, "size")?)
+            .split_whitespace()
+            .map(|n| n.parse())
+            .collect::<Result<_, _>>()?;
+        let size = match size[..] {
+            [w, h] if w > 2 && h > 2 => (w, h),
+            _ => anyhow::bail!("Invalid window size"),
+        };
+
+        let stages = items(child(classifier, "stages")?).collect::<Vec<_>>();
+        for (i, stage) in stages.iter().enumerate() {
+            // Older files link the stages, only a plain chain of them is supported
+            let parent = match stage.children().find(|n| n.has_tag_name("parent")) {
+                Some(_) => number::<i64>(*stage, "parent")?,
+                None => i as i64 - 1,
+            };
+            let next = match stage.children().find(|n| n.has_tag_name("next")) {
+                Some(_) => number::<i64>(*stage, "next")?,
+                None => -1,
+            };
+            if parent < -1 || parent >= stages.len() as i64 || next < -1 || next >= stages.len() as i64 {
+                anyhow::bail!("The stage {i} links to a stage out of range");
+            }
+            if parent != i as i64 - 1 || next != -1 {
+                anyhow::bail!("The stage {i} isn't in a chain, trees of stages are not supported");
+            }
+        }
+
+        let stages = stages.into_iter()
+            .map(|stage| {
+                let trees = items(child(stage, "trees")?)
+                    .map(|tree| {
+                        items(tree)
+                            .map(|node| Ok(TreeNode {
+                                feature: feature(child(node, "feature")?)?,
+                                threshold: number(node, "threshold")?,
+                                left: branch(node, "left_val", "left_node")?,
+                                right: branch(node, "right_val", "right_node")?,
+                            }))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Stage {
+                    trees,
+                    threshold: number::<f32>(stage, "stage_threshold")? - THRESHOLD_EPS,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if stages.is_empty() {
+            anyhow::bail!("The cascade has no stages");
+        }
+        for (i, stage) in stages.iter().enumerate() {
+            for (j, tree) in stage.trees.iter().enumerate() {
+                // Nodes only point further down the list, so evaluating a tree always ends
+                let valid = |node: usize, branch: Branch| match branch {
+                    Branch::Node(next) => node < next && next < tree.len(),
+                    Branch::Leaf(_) => true,
+                };
+                if tree.is_empty() || !tree.iter().enumerate().all(|(k, node)| valid(k, node.left) && valid(k, node.right)) {
+                    anyhow::bail!("The tree {j} of the stage {i} links to a node out of range");
+                }
+                for node in tree {
+                    if let Some(rect) = node.feature.rects.iter().find(|rect| !rect.fits(size, node.feature.tilted)) {
+                        anyhow::bail!(
+                            "The tree {j} of the stage {i} has the rectangle {} {} {} {} outside the {}x{} window",
+                            rect.x, rect.y, rect.w, rect.h, size.0, size.1,
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Cascade { size, stages })
+    }
+
+    /// Finds the objects in a grayscale image, returning each box with the amount of windows grouped into it.
+    pub fn detect(
+        &self,
+        img: &GrayImage,
+        scale_factor: f64,
+        min_neighbors: i32,
+        min_size: (i32, i32),
+        max_size: (i32, i32),
+    ) -> Vec<((i32, i32, i32, i32), i32)> {
+        let (img_w, img_h) = (img.width() as i32, img.height() as i32);
+        let max_size = if max_size.0 <= 0 || max_size.1 <= 0 { (img_w, img_h) } else { max_size };
+
+        let mut windows = Vec::new();
+        let mut factor = 1.;
+        loop {
+            let window = (
+                (self.size.0 as f64 * factor).round() as i32,
+                (self.size.1 as f64 * factor).round() as i32,
+            );
+            let scaled = (
+                (img_w as f64 / factor).round() as i32,
+                (img_h as f64 / factor).round() as i32,
+            );
+            if window.0 > max_size.0 || window.1 > max_size.1 || scaled.0 < self.size.0 || scaled.1 < self.size.1 {
+                break;
+            }
+
+            if window.0 >= min_size.0 && window.1 >= min_size.1 {
+                let resized = if factor == 1. {
+                    img.clone()
+                } else {
+                    imgproc::resize(img, scaled.0 as u32, scaled.1 as u32)
+                };
+                let integral = Integral::new(&resized, self.stages.iter().any(|stage| stage.has_tilted()));
+                // Every position from twice the size on, as `ystep` in OpenCV's `updateScaleData`
+                let step = if factor >= 2. { 1 } else { 2 };
+
+                for y in (0..=scaled.1 - self.size.1).step_by(step) {
+                    for x in (0..=scaled.0 - self.size.0).step_by(step) {
+                        if self.run_at(&integral, x, y) {
+                            windows.push((
+                                (x as f64 * factor).round() as i32,
+                                (y as f64 * factor).round() as i32,
+                                window.0,
+                                window.1,
+                            ));
+                        }
+                    }
+                }
+            }
+
+            factor *= scale_factor;
+        }
+
+        group_rectangles(windows, min_neighbors)
+    }
+
+    /// Whether the window at `(x, y)` passes every stage.
+    fn run_at(&self, integral: &Integral, x: i32, y: i32) -> bool {
+        // Normalise by the standard deviation of the window without its border, like OpenCV
+        let (w, h) = (self.size.0 - 2, self.size.1 - 2);
+        let area = (w * h) as f64;
+        let sum = integral.sum(x + 1, y + 1, w, h) as f64;
+        let sq_sum = integral.sq_sum(x + 1, y + 1, w, h);
+        let norm = area * sq_sum - sum * sum;
+        let norm = if norm > 0. { norm.sqrt() } else { 1. };
+
+        self.stages.iter().all(|stage| {
+            let total: f32 = stage.trees.iter()
+                .map(|tree| {
+                    let mut node = &tree[0];
+                    loop {
+                        let value = node.feature.eval(integral, x, y) / norm;
+                        let branch = if value < node.threshold as f64 { node.left } else { node.right };
+                        match branch {
+                            Branch::Leaf(value) => break value,
+                            Branch::Node(i) => node = &tree[i],
+                        }
+                    }
+                })
+                .sum();
+            total >= stage.threshold
+        })
+    }
+}
+
+impl Stage {
+    fn has_tilted(&self) -> bool {
+        self.trees.iter().flatten().any(|node| node.feature.tilted)
+    }
+}
+
+impl Feature {
+    fn eval(&self, integral: &Integral, x: i32, y: i32) -> f64 {
+        self.rects.iter()
+            .map(|r| {
+                let sum = if self.tilted {
+                    integral.tilted_sum(x + r.x, y + r.y, r.w, r.h)
+                } else {
+                    integral.sum(x + r.x, y + r.y, r.w, r.h)
+                };
+                r.weight as f64 * sum as f64
+            })
+            .sum()
+    }
+}
+
+/// Summed area tables of an image, each one pixel bigger than the image on both axes.
+struct Integral {
+    stride: usize,
+    sum: Vec<i64>,
+    sq_sum: Vec<f64>,
+    /// Sum of the pixels in the upward cone above each point, empty when no feature is tilted.
+    tilted: Vec<i64>,
+}
+
+impl Integral {
+    fn new(img: &GrayImage, tilted: bool) -> Self {
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let stride = width + 1;
+        let pixels = img.as_raw();
+
+        let mut sum = vec![0i64; stride * (height + 1)];
+        let mut sq_sum = vec![0f64; stride * (height + 1)];
+        for y in 0..height {
+            let (mut row, mut sq_row) = (0i64, 0f64);
+            for x in 0..width {
+                let p = pixels[y * width + x] as i64;
+                row += p;
+                sq_row += (p * p) as f64;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sq_sum[(y + 1) * stride + x + 1] = sq_sum[y * stride + x + 1] + sq_row;
+            }
+        }
+
+        let tilted = if tilted { tilted_integral(pixels, width, height) } else { Vec::new() };
+
+        Integral { stride, sum, sq_sum, tilted }
+    }
+
+    fn at<T: Copy>(&self, table: &[T], x: i32, y: i32) -> T {
+        table[y as usize * self.stride + x as usize]
+    }
+
+    fn sum(&self, x: i32, y: i32, w: i32, h: i32) -> i64 {
+        self.at(&self.sum, x + w, y + h) - self.at(&self.sum, x, y + h)
+            - self.at(&self.sum, x + w, y) + self.at(&self.sum, x, y)
+    }
+
+    fn sq_sum(&self, x: i32, y: i32, w: i32, h: i32) -> f64 {
+        self.at(&self.sq_sum, x + w, y + h) - self.at(&self.sq_sum, x, y + h)
+            - self.at(&self.sq_sum, x + w, y) + self.at(&self.sq_sum, x, y)
+    }
+
+    /// Sum of the rectangle rotated 45 degrees with its top corner at `(x, y)`.
+    fn tilted_sum(&self, x: i32, y: i32, w: i32, h: i32) -> i64 {
+        self.at(&self.tilted, x, y) - self.at(&self.tilted, x - h, y + h)
+            - self.at(&self.tilted, x + w, y + w) + self.at(&self.tilted, x + w - h, y + w + h)
+    }
+}
+
+/// For each point `(X, Y)`, the sum of the pixels `(x, y)` with `y < Y` and
+/// `X - (Y - y) <= x <= X + (Y - y) - 2`, same as the tilted sums of `cv::integral`.
+///
+/// Computed as the difference of two diagonal sums of the row prefix sums,
+/// one going up-right and the other up-left.
+fn tilted_integral(pixels: &[u8], width: usize, height: usize) -> Vec<i64> {
+    let stride = width + 1;
+    let mut prefix = vec![0i64; stride * height];
+    let mut totals = vec![0i64; height + 1];
+    for y in 0..height {
+        for x in 0..width {
+            prefix[y * stride + x + 1] = prefix[y * stride + x] + pixels[y * width + x] as i64;
+        }
+        totals[y + 1] = totals[y] + prefix[y * stride + width];
+    }
+
+    let mut right = vec![0i64; stride * (height + 1)];
+    let mut left = vec![0i64; stride * (height + 1)];
+    for y in 1..=height {
+        for x in 0..=width {
+            let row = &prefix[(y - 1) * stride..y * stride];
+            right[y * stride + x] = if x < width {
+                right[(y - 1) * stride + x + 1] + row[x]
+            } else {
+                totals[y]
+            };
+            left[y * stride + x] = if x > 0 {
+                left[(y - 1) * stride + x - 1] + row[x - 1]
+            } else {
+                0
+            };
+        }
+    }
+
+    right.iter().zip(&left).map(|(r, l)| r - l).collect()
+}
+
+fn similar(a: &(i32, i32, i32, i32), b: &(i32, i32, i32, i32)) -> bool {
+    let delta = GROUP_EPS * (a.2.min(b.2) + a.3.min(b.3)) as f64 * 0.5;
+    (a.0 - b.0).abs() as f64 <= delta
+        && (a.1 - b.1).abs() as f64 <= delta
+        && (a.0 + a.2 - b.0 - b.2).abs() as f64 <= delta
+        && (a.1 + a.3 - b.1 - b.3).abs() as f64 <= delta
+}
+
+/// Same as `cv::groupRectangles`: averages the similar windows, drops the groups with
+/// `min_neighbors` windows or less and the groups inside a stronger one.
+fn group_rectangles(windows: Vec<(i32, i32, i32, i32)>, min_neighbors: i32) -> Vec<((i32, i32, i32, i32), i32)> {
+    if min_neighbors <= 0 || windows.is_empty() {
+        return windows.into_iter().map(|window| (window, 1)).collect();
+    }
+
+    // Union-find of the similar windows
+    let mut parent: Vec<usize> = (0..windows.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..windows.len() {
+        for j in 0..i {
+            if similar(&windows[i], &windows[j]) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, [i64; 4], i32)> = Vec::new();
+    for (i, window) in windows.iter().enumerate() {
+        let label = root(&mut parent, i);
+        let group = match groups.iter().position(|(l, _, _)| *l == label) {
+            Some(group) => group,
+            None => {
+                groups.push((label, [0; 4], 0));
+                groups.len() - 1
+            }
+        };
+        let (_, total, count) = &mut groups[group];
+        total[0] += window.0 as i64;
+        total[1] += window.1 as i64;
+        total[2] += window.2 as i64;
+        total[3] += window.3 as i64;
+        *count += 1;
+    }
+
+    let averaged: Vec<((i32, i32, i32, i32), i32)> = groups.into_iter()
+        .map(|(_, total, count)| {
+            let avg = |v: i64| (v as f64 / count as f64).round() as i32;
+            ((avg(total[0]), avg(total[1]), avg(total[2]), avg(total[3])), count)
+        })
+        .collect();
+
+    averaged.iter()
+        .filter(|(r1, n1)| {
+            *n1 > min_neighbors && !averaged.iter().any(|(r2, n2)| {
+                if std::ptr::eq(r1, r2) || *n2 <= min_neighbors {
+                    return false;
+                }
+                let dx = (r2.2 as f64 * GROUP_EPS).round() as i32;
+                let dy = (r2.3 as f64 * GROUP_EPS).round() as i32;
+                r1.0 >= r2.0 - dx
+                    && r1.1 >= r2.1 - dy
+                    && r1.0 + r1.2 <= r2.0 + r2.2 + dx
+                    && r1.1 + r1.3 <= r2.1 + r2.3 + dy
+                    && (*n2 > (*n1).max(3) || *n1 < 3)
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cascade with one stage of a single node, in the format of the files OpenCV ships.
+    fn cascade(size: &str, rects: &str, tilted: bool, right: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+<opencv_storage>
+<fixture type_id="opencv-haar-classifier">
+  <size>{size}</size>
+  <stages>
+    <_>
+      <trees>
+        <_>
+          <_>
+            <feature>
+              <rects>{rects}</rects>
+              <tilted>{}</tilted></feature>
+            <threshold>3.</threshold>
+            <left_val>-1.</left_val>
+            {right}</_></_></trees>
+      <stage_threshold>0.</stage_threshold>
+      <parent>-1</parent>
+      <next>-1</next></_></stages></fixture>
+</opencv_storage>"#,
+            tilted as u8,
+        )
+    }
+
+    /// Finds a bright 2x2 block in the middle of a 6x6 window.
+    fn spot() -> String {
+        cascade("6 6", "<_>0 0 6 6 -1.</_><_>2 2 2 2 9.</_>", false, "<right_val>1.</right_val>")
+    }
+
+    /// Pixels that aren't all alike, so a wrong sum doesn't match by chance.
+    fn noise(width: u32, height: u32) -> GrayImage {
+        let mut seed = 7u32;
+        GrayImage::from_fn(width, height, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            image::Luma([(seed >> 16) as u8])
+        })
+    }
+
+    #[test]
+    fn tilted_integral_sums_the_cone_above() {
+        let img = noise(9, 7);
+        let integral = Integral::new(&img, true);
+        for y in 0..=7i32 {
+            for x in 0..=9i32 {
+                let expected: i64 = img.enumerate_pixels()
+                    .filter(|(px, py, _)| {
+                        let (px, py) = (*px as i32, *py as i32);
+                        py < y && x - (y - py) <= px && px <= x + (y - py) - 2
+                    })
+                    .map(|(_, _, p)| p[0] as i64)
+                    .sum();
+                assert_eq!(integral.at(&integral.tilted, x, y), expected, "cone at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn tilted_sum_matches_the_rotated_rectangle() {
+        let img = noise(12, 10);
+        let integral = Integral::new(&img, true);
+        for y in 0..10 {
+            for x in 0..12 {
+                for w in 1..6 {
+                    for h in 1..6 {
+                        let rect = Rect { x, y, w, h, weight: 1. };
+                        if !rect.fits((12, 10), true) {
+                            continue;
+                        }
+                        // Rotated 45 degrees, the sides run along the diagonals `px + py` and `py - px`
+                        let expected: i64 = img.enumerate_pixels()
+                            .filter(|(px, py, _)| {
+                                let (u, v) = (*px as i32 + *py as i32, *py as i32 - *px as i32);
+                                x + y - 2 < u && u <= x + y + 2 * w - 2 && y - x < v && v <= y - x + 2 * h
+                            })
+                            .map(|(_, _, p)| p[0] as i64)
+                            .sum();
+                        assert_eq!(integral.tilted_sum(x, y, w, h), expected, "rectangle {x} {y} {w} {h}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_the_builtin_cascade() {
+        let cascade = Cascade::parse(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/cars.xml"))).unwrap();
+        assert_eq!(cascade.size, (20, 20));
+        assert_eq!(cascade.stages.len(), 13);
+        assert_eq!(cascade.stages.iter().flat_map(|stage| stage.trees.iter().flatten()).count(), 250);
+        assert!(!cascade.stages.iter().any(Stage::has_tilted));
+    }
+
+    #[test]
+    fn rejects_rectangles_outside_the_window() {
+        let leaf = "<right_val>1.</right_val>";
+        assert!(Cascade::parse(&cascade("6 6", "<_>2 2 5 2 1.</_>", false, leaf)).is_err());
+        assert!(Cascade::parse(&cascade("6 6", "<_>2 -1 2 2 1.</_>", false, leaf)).is_err());
+        // Tilted rectangles reach `h` to the left of their corner and `w + h` below it
+        assert!(Cascade::parse(&cascade("6 6", "<_>1 0 2 2 1.</_>", true, leaf)).is_err());
+        assert!(Cascade::parse(&cascade("6 6", "<_>2 3 2 2 1.</_>", true, leaf)).is_err());
+        assert!(Cascade::parse(&cascade("6 6", "<_>2 0 2 2 1.</_>", true, leaf)).is_ok());
+    }
+
+    #[test]
+    fn rejects_nodes_out_of_range() {
+        let rects = "<_>0 0 6 6 1.</_>";
+        assert!(Cascade::parse(&cascade("6 6", rects, false, "<right_node>1</right_node>")).is_err());
+        // A node pointing to itself would never end
+        assert!(Cascade::parse(&cascade("6 6", rects, false, "<right_node>0</right_node>")).is_err());
+        let unlinked = cascade("6 6", rects, false, "<right_val>1.</right_val>").replace("<parent>-1", "<parent>3");
+        assert!(Cascade::parse(&unlinked).is_err());
+    }
+
+    #[test]
+    fn load_fails_with_a_missing_cascade() {
+        let err = Cascade::load(Path::new("/nonexistent/cascade.xml")).unwrap_err();
+        assert!(matches!(err, DetectError::MissingCascade(_)));
+    }
+
+    #[test]
+    fn groups_similar_windows() {
+        let windows = vec![(10, 10, 20, 20), (11, 10, 20, 20), (10, 12, 20, 21), (100, 100, 20, 20)];
+        assert_eq!(group_rectangles(windows.clone(), 0).len(), 4);
+        assert_eq!(group_rectangles(windows.clone(), 2), vec![((10, 11, 20, 20), 3)]);
+        assert!(group_rectangles(windows, 3).is_empty());
+    }
+
+    #[test]
+    fn groups_windows_within_eps() {
+        // Windows of 20 pixels are similar up to 4 pixels apart
+        let close = vec![(0, 0, 20, 20), (4, 0, 20, 20)];
+        assert_eq!(group_rectangles(close, 1), vec![((2, 0, 20, 20), 2)]);
+        let apart = vec![(0, 0, 20, 20), (0, 0, 20, 20), (5, 0, 20, 20), (5, 0, 20, 20)];
+        assert_eq!(group_rectangles(apart, 1), vec![((0, 0, 20, 20), 2), ((5, 0, 20, 20), 2)]);
+    }
+
+    #[test]
+    fn detects_the_fixture() {
+        let cascade = Cascade::parse(&spot()).unwrap();
+        let mut img = GrayImage::new(30, 20);
+        for (x, y) in [(10, 6), (20, 12)] {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                img.put_pixel(x + dx, y + dy, image::Luma([255]));
+            }
+        }
+
+        // Only the windows with the block in their middle, at the original scale
+        assert_eq!(
+            cascade.detect(&img, 1.1, 0, (0, 0), (6, 6)),
+            vec![((8, 4, 6, 6), 1), ((18, 10, 6, 6), 1)],
+        );
+        assert!(cascade.detect(&GrayImage::new(30, 20), 1.1, 0, (0, 0), (0, 0)).is_empty());
+    }
+
+    #[test]
+    fn scans_every_position_at_twice_the_size() {
+        let cascade = Cascade::parse(&spot()).unwrap();
+        // Halved, the block is in the middle of the window at (3, 3), an odd position
+        let mut img = GrayImage::new(24, 24);
+        for y in 10..14 {
+            for x in 10..14 {
+                img.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+
+        // Only the scale 2, with windows of 12 pixels
+        assert_eq!(cascade.detect(&img, 2., 0, (12, 12), (12, 12)), vec![((6, 6, 12, 12), 1)]);
+    }
+}
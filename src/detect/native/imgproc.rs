@@ -0,0 +1,411 @@
+//! The few OpenCV image operations the methods need, following OpenCV's defaults.
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+/// Same weights and rounding as `cv::cvtColor` with `COLOR_BGR2GRAY`.
+pub fn grayscale(img: &RgbImage) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let Rgb([r, g, b]) = *img.get_pixel(x, y);
+        let y = (r as u32 * 4899 + g as u32 * 9617 + b as u32 * 1868 + (1 << 13)) >> 14;
+        Luma([y as u8])
+    })
+}
+
+/// Same kernel as `cv::getGaussianKernel` with a sigma computed from the size.
+fn gaussian_kernel(ksize: usize) -> Vec<f64> {
+    match ksize {
+        1 => vec![1.],
+        3 => vec![0.25, 0.5, 0.25],
+        5 => vec![0.0625, 0.25, 0.375, 0.25, 0.0625],
+        7 => vec![0.03125, 0.109375, 0.21875, 0.28125, 0.21875, 0.109375, 0.03125],
+        _ => {
+            let sigma = 0.3 * ((ksize as f64 - 1.) * 0.5 - 1.) + 0.8;
+            let center = (ksize / 2) as f64;
+            let kernel: Vec<f64> = (0..ksize)
+                .map(|i| (-(i as f64 - center).powi(2) / (2. * sigma * sigma)).exp())
+                .collect();
+            let sum: f64 = kernel.iter().sum();
+            kernel.into_iter().map(|k| k / sum).collect()
+        }
+    }
+}
+
+/// Index mirrored around the edges without repeating them, OpenCV's `BORDER_REFLECT_101`.
+fn reflect_101(i: isize, len: usize) -> usize {
+    let len = len as isize;
+    if len == 1 {
+        return 0;
+    }
+    let mut i = i;
+    while i < 0 || i >= len {
+        i = if i < 0 { -i } else { 2 * (len - 1) - i };
+    }
+    i as usize
+}
+
+/// `cv::GaussianBlur` with a square kernel of odd size and sigma 0.
+pub fn gaussian_blur(img: &GrayImage, ksize: u32) -> GrayImage {
+    let kernel = gaussian_kernel(ksize as usize);
+    let radius = (ksize / 2) as isize;
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let src = img.as_raw();
+
+    let mut rows = vec![0f64; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * width + x] = kernel.iter().enumerate()
+                .map(|(k, w)| w * src[y * width + reflect_101(x as isize + k as isize - radius, width)] as f64)
+                .sum();
+        }
+    }
+
+    GrayImage::from_fn(width as u32, height as u32, |x, y| {
+        let value: f64 = kernel.iter().enumerate()
+            .map(|(k, w)| w * rows[reflect_101(y as isize + k as isize - radius, height) * width + x as usize])
+            .sum();
+        Luma([value.round().clamp(0., 255.) as u8])
+    })
+}
+
+/// Binary mask of a structuring element, indexed as `[row][column]`.
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    pub rows: usize,
+    pub cols: usize,
+    mask: Vec<bool>,
+}
+
+impl Kernel {
+    /// `np.ones((rows, cols))`, same as `cv::MORPH_RECT`.
+    pub fn rect(rows: usize, cols: usize) -> Self {
+        Kernel { rows, cols, mask: vec![true; rows * cols] }
+    }
+
+    /// Same as `cv::getStructuringElement` with `cv::MORPH_ELLIPSE`.
+    pub fn ellipse(rows: usize, cols: usize) -> Self {
+        let (r, c) = ((rows / 2) as isize, (cols / 2) as isize);
+        let inv_r2 = if r > 0 { 1. / (r * r) as f64 } else { 0. };
+        let mut mask = vec![false; rows * cols];
+
+        for i in 0..rows as isize {
+            let dy = i - r;
+            if dy.abs() > r {
+                continue;
+            }
+            let dx = if r > 0 {
+                (c as f64 * (((r * r - dy * dy) as f64) * inv_r2).sqrt()).round() as isize
+            } else {
+                c
+            };
+            let j1 = (c - dx).max(0);
+            let j2 = (c + dx + 1).min(cols as isize);
+            for j in j1..j2 {
+                mask[i as usize * cols + j as usize] = true;
+            }
+        }
+
+        Kernel { rows, cols, mask }
+    }
+
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        let (ay, ax) = ((self.rows / 2) as isize, (self.cols / 2) as isize);
+        (0..self.rows)
+            .flat_map(|i| (0..self.cols).map(move |j| (i, j)))
+            .filter(|(i, j)| self.mask[i * self.cols + j])
+            .map(|(i, j)| (i as isize - ay, j as isize - ax))
+            .collect()
+    }
+}
+
+/// Replaces each pixel by the maximum (`dilate`) or minimum of its neighbourhood,
+/// pixels outside of the image are ignored.
+fn morph(img: &GrayImage, kernel: &Kernel, dilate: bool) -> GrayImage {
+    let offsets = kernel.offsets();
+    let (width, height) = (img.width() as isize, img.height() as isize);
+
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let neighbours = offsets.iter()
+            .map(|(dy, dx)| (x as isize + dx, y as isize + dy))
+            .filter(|(nx, ny)| (0..width).contains(nx) && (0..height).contains(ny))
+            .map(|(nx, ny)| img.get_pixel(nx as u32, ny as u32)[0]);
+        let value = if dilate { neighbours.max() } else { neighbours.min() };
+        Luma([value.unwrap_or(img.get_pixel(x, y)[0])])
+    })
+}
+
+pub fn dilate(img: &GrayImage, kernel: &Kernel, iterations: u32) -> GrayImage {
+    let mut img = img.clone();
+    for _ in 0..iterations {
+        img = morph(&img, kernel, true);
+    }
+    img
+}
+
+pub fn erode(img: &GrayImage, kernel: &Kernel, iterations: u32) -> GrayImage {
+    let mut img = img.clone();
+    for _ in 0..iterations {
+        img = morph(&img, kernel, false);
+    }
+    img
+}
+
+/// `cv::MORPH_OPEN`, erosion followed by dilation.
+pub fn open(img: &GrayImage, kernel: &Kernel) -> GrayImage {
+    dilate(&erode(img, kernel, 1), kernel, 1)
+}
+
+/// `cv::MORPH_CLOSE`, dilation followed by erosion.
+pub fn close(img: &GrayImage, kernel: &Kernel) -> GrayImage {
+    erode(&dilate(img, kernel, 1), kernel, 1)
+}
+
+/// `cv::resize` with `cv::INTER_LINEAR` over interleaved pixels with `channels` channels.
+fn resize_raw(src: &[u8], channels: usize, (src_w, src_h): (u32, u32), (width, height): (u32, u32)) -> Vec<u8> {
+    let (fx, fy) = (src_w as f64 / width as f64, src_h as f64 / height as f64);
+    let sample = |dst: u32, scale: f64, len: u32| -> (usize, usize, f64) {
+        let pos = ((dst as f64 + 0.5) * scale - 0.5).max(0.);
+        let i0 = (pos.floor() as u32).min(len - 1);
+        let i1 = (i0 + 1).min(len - 1);
+        (i0 as usize, i1 as usize, pos - i0 as f64)
+    };
+
+    let columns: Vec<_> = (0..width).map(|x| sample(x, fx, src_w)).collect();
+    let stride = src_w as usize * channels;
+    let mut dst = Vec::with_capacity(width as usize * height as usize * channels);
+    for y in 0..height {
+        let (y0, y1, ay) = sample(y, fy, src_h);
+        for &(x0, x1, ax) in &columns {
+            for c in 0..channels {
+                let p = |x: usize, y: usize| src[y * stride + x * channels + c] as f64;
+                let top = p(x0, y0) * (1. - ax) + p(x1, y0) * ax;
+                let bottom = p(x0, y1) * (1. - ax) + p(x1, y1) * ax;
+                dst.push((top * (1. - ay) + bottom * ay).round().clamp(0., 255.) as u8);
+            }
+        }
+    }
+    dst
+}
+
+pub fn resize(img: &GrayImage, width: u32, height: u32) -> GrayImage {
+    let data = resize_raw(img.as_raw(), 1, img.dimensions(), (width, height));
+    GrayImage::from_raw(width, height, data).expect("resized buffer has the requested size")
+}
+
+pub fn resize_rgb(img: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let data = resize_raw(img.as_raw(), 3, img.dimensions(), (width, height));
+    RgbImage::from_raw(width, height, data).expect("resized buffer has the requested size")
+}
+
+/// `cv::threshold` with `cv::THRESH_BINARY` and a maximum of 255.
+pub fn threshold(img: &GrayImage, threshold: u8) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        Luma([if img.get_pixel(x, y)[0] > threshold { 255 } else { 0 }])
+    })
+}
+
+/// `cv::absdiff` of two images of the same size.
+pub fn absdiff(a: &GrayImage, b: &GrayImage) -> GrayImage {
+    GrayImage::from_fn(a.width(), a.height(), |x, y| {
+        Luma([a.get_pixel(x, y)[0].abs_diff(b.get_pixel(x, y)[0])])
+    })
+}
+
+/// Bounding box and pixel count of a connected component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Component {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub area: i32,
+}
+
+/// `cv::connectedComponentsWithStats` with 8-connectivity, without the background.
+///
+/// Components are numbered in the order their first pixel appears scanning by rows.
+pub fn connected_components(img: &GrayImage) -> Vec<Component> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let pixels = img.as_raw();
+    let mut seen = vec![false; width * height];
+    let mut components = vec![];
+    let mut stack = vec![];
+
+    for start in 0..width * height {
+        if pixels[start] == 0 || seen[start] {
+            continue;
+        }
+
+        seen[start] = true;
+        stack.push(start);
+        let (mut x0, mut y0, mut x1, mut y1, mut area) = (width, height, 0, 0, 0);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+            area += 1;
+
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let n = ny * width + nx;
+                    if pixels[n] != 0 && !seen[n] {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        components.push(Component {
+            x: x0 as i32,
+            y: y0 as i32,
+            w: (x1 - x0 + 1) as i32,
+            h: (y1 - y0 + 1) as i32,
+            area,
+        });
+    }
+
+    components
+}
+
+/// Draws the outline of a rectangle, growing the border inwards and outwards like `cv::rectangle`.
+pub fn draw_rect(img: &mut RgbImage, (x, y, w, h): (i32, i32, i32, i32), color: Rgb<u8>, thickness: i32) {
+    let (width, height) = (img.width() as i32, img.height() as i32);
+    let half = thickness / 2;
+    let mut put = |px: i32, py: i32| {
+        if (0..width).contains(&px) && (0..height).contains(&py) {
+            img.put_pixel(px as u32, py as u32, color);
+        }
+    };
+
+    let (x1, y1) = (x + w, y + h);
+    for t in -half..thickness - half {
+        for px in x - half..=x1 + half {
+            put(px, y + t);
+            put(px, y1 + t);
+        }
+        for py in y - half..=y1 + half {
+            put(x + t, py);
+            put(x1 + t, py);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image from rows of pixels, nonzero where the text has a `#`.
+    fn mask(rows: &[&str]) -> GrayImage {
+        GrayImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            Luma([if rows[y as usize].as_bytes()[x as usize] == b'#' { 255 } else { 0 }])
+        })
+    }
+
+    #[test]
+    fn grayscale_rounds_like_opencv() {
+        let img = RgbImage::from_fn(5, 1, |x, _| {
+            [Rgb([0, 0, 0]), Rgb([255, 255, 255]), Rgb([255, 0, 0]), Rgb([0, 255, 0]), Rgb([0, 0, 255])][x as usize]
+        });
+        assert_eq!(grayscale(&img).as_raw(), &[0, 255, 76, 150, 29]);
+    }
+
+    #[test]
+    fn gaussian_kernels_are_normalised_and_symmetric() {
+        for ksize in (1..=15).step_by(2) {
+            let kernel = gaussian_kernel(ksize);
+            assert_eq!(kernel.len(), ksize);
+            assert!((kernel.iter().sum::<f64>() - 1.).abs() < 1e-9, "kernel {ksize} sums to 1");
+            assert!(kernel.iter().zip(kernel.iter().rev()).all(|(a, b)| (a - b).abs() < 1e-12), "kernel {ksize} is symmetric");
+        }
+    }
+
+    #[test]
+    fn gaussian_blur_spreads_a_point() {
+        let mut img = GrayImage::new(5, 5);
+        img.put_pixel(2, 2, Luma([255]));
+        let blurred = gaussian_blur(&img, 3);
+        assert_eq!(blurred.get_pixel(2, 2)[0], 64);
+        assert_eq!(blurred.get_pixel(1, 2)[0], 32);
+        assert_eq!(blurred.get_pixel(1, 1)[0], 16);
+        assert_eq!(blurred.get_pixel(0, 0)[0], 0);
+
+        let flat = GrayImage::from_pixel(6, 4, Luma([90]));
+        assert_eq!(gaussian_blur(&flat, 7), flat);
+    }
+
+    #[test]
+    fn threshold_keeps_values_above() {
+        let img = GrayImage::from_raw(4, 1, vec![0, 127, 128, 255]).unwrap();
+        assert_eq!(threshold(&img, 127).as_raw(), &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn absdiff_is_symmetric() {
+        let a = GrayImage::from_raw(3, 1, vec![10, 200, 50]).unwrap();
+        let b = GrayImage::from_raw(3, 1, vec![30, 100, 50]).unwrap();
+        assert_eq!(absdiff(&a, &b).as_raw(), &[20, 100, 0]);
+        assert_eq!(absdiff(&b, &a), absdiff(&a, &b));
+    }
+
+    #[test]
+    fn ellipse_matches_opencv() {
+        let mut point = GrayImage::new(7, 7);
+        point.put_pixel(3, 3, Luma([255]));
+        // Dilating a single point draws the structuring element around it
+        let expected = mask(&[
+            ".......",
+            "...#...",
+            ".#####.",
+            ".#####.",
+            ".#####.",
+            "...#...",
+            ".......",
+        ]);
+        assert_eq!(dilate(&point, &Kernel::ellipse(5, 5), 1), expected);
+    }
+
+    #[test]
+    fn open_removes_specks() {
+        let img = mask(&[
+            "..........",
+            ".#........",
+            "...#####..",
+            "...#####..",
+            "...#####..",
+            "...#####..",
+            "...#####..",
+            "..........",
+        ]);
+        // The 3x3 ellipse is a cross, so opening rounds the corners of the square
+        let expected = mask(&[
+            "..........",
+            "..........",
+            "....###...",
+            "...#####..",
+            "...#####..",
+            "...#####..",
+            "....###...",
+            "..........",
+        ]);
+        assert_eq!(open(&img, &Kernel::ellipse(3, 3)), expected);
+    }
+
+    #[test]
+    fn connected_components_use_8_connectivity() {
+        let img = mask(&[
+            "##......",
+            "..#...##",
+            "......##",
+            "........",
+            "#.......",
+        ]);
+        assert_eq!(connected_components(&img), vec![
+            Component { x: 0, y: 0, w: 3, h: 2, area: 3 },
+            Component { x: 6, y: 1, w: 2, h: 2, area: 4 },
+            Component { x: 0, y: 4, w: 1, h: 1, area: 1 },
+        ]);
+        assert!(connected_components(&GrayImage::new(4, 4)).is_empty());
+    }
+}
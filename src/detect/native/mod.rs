@@ -0,0 +1,174 @@
+//! Pure Rust implementation of the methods, enabled by the `native` feature.
+//!
+//! Needs neither Python nor OpenCV, images are read and written with the `image` crate.
+
+use std::{borrow::Cow, io::Cursor, path::Path};
+
+use anyhow::Result;
+use image::{ImageFormat, ImageOutputFormat, Rgb, RgbImage};
+
+use super::{cache::FileCache, error::check_sizes, load, roi, Backend, DiffParams, Detection, DetectError, HaarParams, Outcome, Region, VideoOutcome};
+
+mod haar;
+mod imgproc;
+
+pub use haar::Cascade;
+
+/// Cascades already parsed, reused by later detections.
+static CASCADES: FileCache<Cascade> = FileCache::new();
+
+fn read(img: &Path) -> Result<RgbImage> {
+    let decoded = image::load_from_memory(&load(img)?)
+        .map_err(|err| DetectError::UnreadableImage(format!("{} ({err})", img.display())))?;
+    Ok(decoded.into_rgb8())
+}
+
+fn encode(img: &RgbImage, ext: &str) -> Result<Vec<u8>> {
+    let format = ImageFormat::from_extension(ext)
+        .ok_or_else(|| DetectError::UnsupportedFormat(ext.to_owned()))?;
+    let mut encoded = Cursor::new(vec![]);
+    img.write_to(&mut encoded, ImageOutputFormat::from(format))
+        .map_err(|err| DetectError::EncodeFailure(format!("{ext} ({err})")))?;
+    Ok(encoded.into_inner())
+}
+
+/// Copy of `img` black outside the regions, `img` itself without regions.
+fn masked<'a>(img: &'a RgbImage, regions: &[Region]) -> Cow<'a, RgbImage> {
+    if regions.is_empty() {
+        return Cow::Borrowed(img);
+    }
+    let mut masked = img.clone();
+    let mask = roi::mask(regions, img.width(), img.height());
+    for (pixel, &keep) in masked.pixels_mut().zip(&mask) {
+        if keep == 0 {
+            *pixel = Rgb([0, 0, 0]);
+        }
+    }
+    Cow::Owned(masked)
+}
+
+pub fn diff_n_conn(img1: &Path, img2: &Path, ext: &str, params: &DiffParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    let (mut img1, img2) = (read(img1)?, read(img2)?);
+    check_sizes(img1.dimensions(), img2.dimensions())?;
+
+    // Working copies with the longest side of `work_size` pixels, keeping the aspect ratio
+    let (width, height) = (img1.width() as i32, img1.height() as i32);
+    let scale = params.work_size as f64 / width.max(height) as f64;
+    let size = (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    );
+    let img1_small = imgproc::resize_rgb(&masked(&img1, regions), size.0, size.1);
+    let img2_small = imgproc::resize_rgb(&masked(&img2, regions), size.0, size.1);
+
+    // Convert to grayscale and apply Gaussian blur
+    let img1_blur = imgproc::gaussian_blur(&imgproc::grayscale(&img1_small), params.blur as u32);
+    let img2_blur = imgproc::gaussian_blur(&imgproc::grayscale(&img2_small), params.blur as u32);
+
+    // Binary thresholding of the images
+    let img1_thresh = imgproc::threshold(&img1_blur, params.threshold as u8);
+    let img2_thresh = imgproc::threshold(&img2_blur, params.threshold as u8);
+
+    // Get difference between images
+    let img_diff = imgproc::absdiff(&img1_thresh, &img2_thresh);
+
+    // Reduce noise and dilate the image
+    let kernel = imgproc::Kernel::rect(params.kernel.0 as usize, params.kernel.1 as usize);
+    let opened = imgproc::open(&img_diff, &kernel);
+    let dilated = imgproc::dilate(&opened, &kernel, params.dilate_iterations as u32);
+
+    // Draw the bounding boxes of the components on the original image
+    let thickness = ((2. / scale).round() as i32).max(2);
+    let mut detections = vec![];
+    for c in imgproc::connected_components(&dilated) {
+        let (x0, y0) = ((c.x as f64 / scale) as i32, (c.y as f64 / scale) as i32);
+        let x1 = (((c.x + c.w) as f64 / scale).ceil() as i32).min(width);
+        let y1 = (((c.y + c.h) as f64 / scale).ceil() as i32).min(height);
+        let rect = (x0, y0, x1 - x0, y1 - y0);
+
+        detections.push(Detection::new("diff", (rect.0, rect.1, rect.2, rect.3, c.area as f32 / (c.w * c.h) as f32)));
+        imgproc::draw_rect(&mut img1, rect, Rgb([0, 255, 0]), thickness);
+    }
+
+    Ok(Outcome { detections, image: encode(&img1, ext)? })
+}
+
+pub fn haar_cascade(img: &Path, ext: &str, xml: &Path, params: &HaarParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    let mut img = read(img)?;
+
+    let blur = imgproc::gaussian_blur(&imgproc::grayscale(&masked(&img, regions)), params.blur as u32);
+    let dilated = imgproc::dilate(&blur, &imgproc::Kernel::rect(params.dilate as usize, params.dilate as usize), 1);
+    let closing = imgproc::close(&dilated, &imgproc::Kernel::ellipse(params.close as usize, params.close as usize));
+
+    let cars = CASCADES.with(xml, |xml| Ok(Cascade::load(xml)?), |cascade| {
+        cascade.detect(&closing, params.scale_factor, params.min_neighbors, params.min_size, params.max_size)
+    })?;
+
+    let mut detections = vec![];
+    for ((x, y, w, h), n) in cars {
+        imgproc::draw_rect(&mut img, (x, y, w, h), Rgb([0, 0, 255]), 2);
+        detections.push(Detection::new("haar", (x, y, w, h, n as f32)));
+    }
+
+    Ok(Outcome { detections, image: encode(&img, ext)? })
+}
+
+/// Videos need a decoder this backend doesn't have.
+pub fn haar_video(_video: &Path, _output: &Path, _fourcc: &str, _xml: &Path, _params: &HaarParams, _regions: &[Region]) -> Result<VideoOutcome> {
+    Err(DetectError::VideoUnsupported(Backend::Native.name()).into())
+}
+
+/// Videos need a decoder this backend doesn't have.
+pub fn diff_video(_video: &Path, _reference: Option<&Path>, _output: &Path, _fourcc: &str, _params: &DiffParams, _regions: &[Region]) -> Result<VideoOutcome> {
+    Err(DetectError::VideoUnsupported(Backend::Native.name()).into())
+}
+
+/// Videos need a decoder this backend doesn't have.
+pub fn first_frame(_video: &Path) -> Result<Vec<u8>> {
+    Err(DetectError::VideoUnsupported(Backend::Native.name()).into())
+}
+
+/// MOG2 needs OpenCV's background subtractor, which this backend doesn't have.
+pub fn mog2_background(_frames: &[&Path]) -> Result<Vec<u8>> {
+    Err(DetectError::Mog2Unsupported(Backend::Native.name()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::fixtures::{self, TempImage};
+
+    /// Flat gray frame with the given bright rectangles.
+    fn frame(name: &str, rects: &[(u32, u32, u32, u32)]) -> TempImage {
+        let img = RgbImage::from_fn(200, 100, |x, y| {
+            let bright = rects.iter().any(|&(rx, ry, rw, rh)| (rx..rx + rw).contains(&x) && (ry..ry + rh).contains(&y));
+            if bright { Rgb([230, 230, 230]) } else { Rgb([60, 60, 60]) }
+        });
+        fixtures::png(&format!("native-{name}"), &img)
+    }
+
+    #[test]
+    fn diff_n_conn_finds_the_new_rectangles() {
+        let rects = [(20, 30, 40, 20), (120, 50, 30, 35)];
+        let (empty, cars) = (frame("empty", &[]), frame("cars", &rects));
+
+        // Without blur nor morphology at the original size, the boxes are the rectangles
+        let exact = DiffParams { work_size: 200, blur: 1, kernel: (1, 1), dilate_iterations: 0, ..DiffParams::default() };
+        let outcome = diff_n_conn(&empty, &cars, "png", &exact, &[]).unwrap();
+        let boxes: Vec<_> = outcome.detections.iter().map(|d| (d.x, d.y, d.w, d.h, d.score)).collect();
+        assert_eq!(boxes, vec![(20, 30, 40, 20, 1.), (120, 50, 30, 35, 1.)]);
+
+        // The defaults grow the boxes but keep them apart
+        let outcome = diff_n_conn(&empty, &cars, "png", &DiffParams::default(), &[]).unwrap();
+        assert_eq!(outcome.detections.len(), 2);
+        for (d, &(x, y, w, h)) in outcome.detections.iter().zip(&rects) {
+            let (x, y, w, h) = (x as i32, y as i32, w as i32, h as i32);
+            assert!(d.x <= x && d.y <= y && d.x + d.w >= x + w && d.y + d.h >= y + h, "{d:?} covers {:?}", (x, y, w, h));
+        }
+
+        let same = diff_n_conn(&empty, &empty, "png", &DiffParams::default(), &[]).unwrap();
+        assert!(same.detections.is_empty());
+    }
+}
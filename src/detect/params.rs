@@ -0,0 +1,162 @@
+//! Tunable parameters of the counting methods.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Parameters of the Haar Cascade method.
+///
+/// The preprocessing kernels are applied in order: Gaussian blur, dilation with a square
+/// kernel and closing with an elliptical kernel. Sizes of `(0, 0)` mean no limit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HaarParams {
+    /// How much the image shrinks between each scale, must be greater than 1.
+    pub scale_factor: f64,
+    /// Overlapping windows needed to keep a detection.
+    pub min_neighbors: i32,
+    /// Smallest object to look for, as `(width, height)`.
+    pub min_size: (i32, i32),
+    /// Biggest object to look for, as `(width, height)`.
+    pub max_size: (i32, i32),
+    /// Size of the Gaussian blur kernel, must be odd.
+    pub blur: i32,
+    /// Size of the dilation kernel.
+    pub dilate: i32,
+    /// Size of the closing kernel.
+    pub close: i32,
+}
+
+impl Default for HaarParams {
+    fn default() -> Self {
+        HaarParams {
+            scale_factor: 1.1,
+            min_neighbors: 1,
+            min_size: (0, 0),
+            max_size: (0, 0),
+            blur: 5,
+            dilate: 3,
+            close: 2,
+        }
+    }
+}
+
+impl HaarParams {
+    /// Named presets, the first one is the default.
+    pub const PRESETS: &'static [&'static str] = &["default", "sensitive", "strict"];
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(HaarParams::default()),
+            // Finer scale steps and no neighbour filtering, finds more cars and more false positives
+            "sensitive" => Some(HaarParams {
+                scale_factor: 1.05,
+                min_neighbors: 0,
+                ..HaarParams::default()
+            }),
+            // Only keeps detections confirmed by several windows, ignoring tiny objects
+            "strict" => Some(HaarParams {
+                scale_factor: 1.1,
+                min_neighbors: 4,
+                min_size: (30, 30),
+                ..HaarParams::default()
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.scale_factor <= 1. {
+            anyhow::bail!("The scale factor must be greater than 1");
+        }
+        if self.min_neighbors < 0 {
+            anyhow::bail!("The minimum neighbours can't be negative");
+        }
+        if self.blur < 1 || self.blur % 2 == 0 {
+            anyhow::bail!("The blur kernel size must be odd");
+        }
+        if self.dilate < 1 || self.close < 1 {
+            anyhow::bail!("The kernel sizes must be at least 1");
+        }
+        if self.max_size != (0, 0) && (self.max_size.0 < self.min_size.0 || self.max_size.1 < self.min_size.1) {
+            anyhow::bail!("The maximum size must be bigger than the minimum size");
+        }
+        Ok(())
+    }
+}
+
+/// Parameters of the Diff & Connect method.
+///
+/// Both images are scaled down to a working copy, blurred and thresholded before taking their
+/// difference, which is then opened and dilated with a rectangular kernel to join the parts of
+/// each car. The boxes are mapped back and drawn on the first image at its original resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiffParams {
+    /// Longest side of the working copy, the aspect ratio of the first image is kept.
+    ///
+    /// The kernel sizes are relative to it, so a fixed value gives the same results for
+    /// any camera resolution.
+    pub work_size: i32,
+    /// Size of the Gaussian blur kernel, must be odd.
+    pub blur: i32,
+    /// Gray level that separates black from white, from 0 to 255.
+    pub threshold: i32,
+    /// Size of the opening and dilation kernel, as `(rows, columns)`.
+    pub kernel: (i32, i32),
+    /// Times the dilation is applied.
+    pub dilate_iterations: i32,
+}
+
+impl Default for DiffParams {
+    fn default() -> Self {
+        DiffParams {
+            work_size: 500,
+            blur: 5,
+            threshold: 127,
+            kernel: (8, 2),
+            dilate_iterations: 5,
+        }
+    }
+}
+
+impl DiffParams {
+    /// Named presets, the first one is the default.
+    pub const PRESETS: &'static [&'static str] = &["default", "sensitive", "strict"];
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(DiffParams::default()),
+            // Smaller kernel, keeps small changes and may split a car in several parts
+            "sensitive" => Some(DiffParams {
+                kernel: (4, 2),
+                dilate_iterations: 3,
+                ..DiffParams::default()
+            }),
+            // Stronger blur and bigger kernel, only keeps big changes
+            "strict" => Some(DiffParams {
+                blur: 9,
+                kernel: (12, 4),
+                dilate_iterations: 6,
+                ..DiffParams::default()
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.work_size < 1 {
+            anyhow::bail!("The working size must be at least 1");
+        }
+        if self.blur < 1 || self.blur % 2 == 0 {
+            anyhow::bail!("The blur kernel size must be odd");
+        }
+        if !(0..=255).contains(&self.threshold) {
+            anyhow::bail!("The threshold must be between 0 and 255");
+        }
+        if self.kernel.0 < 1 || self.kernel.1 < 1 {
+            anyhow::bail!("The kernel sizes must be at least 1");
+        }
+        if self.dilate_iterations < 0 {
+            anyhow::bail!("The dilation iterations can't be negative");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::Path;
+
+use anyhow::Result;
+use pyo3::{once_cell::GILOnceCell, prelude::*, types::{IntoPyDict, PyBytes, PyDict}};
+
+use super::{doctor::Check, load, roi::RegionKind, DiffParams, Detection, DetectError, HaarParams, Outcome, Region, VideoOutcome};
+
+/// Boxes as `(x, y, w, h, score)` and the encoded annotated image, as returned by the scripts.
+type ScriptResult<'py> = (Vec<(i32, i32, i32, i32, f32)>, &'py PyBytes);
+
+/// Boxes of every frame and the frame rate, as returned by the video functions of the scripts.
+type VideoResult = (Vec<Vec<(i32, i32, i32, i32, f32)>>, f64);
+
+fn into_video_outcome(method: &str, (frames, fps): VideoResult, output: &Path) -> VideoOutcome {
+    let frames = frames.into_iter()
+        .map(|boxes| boxes.into_iter().map(|b| Detection::new(method, b)).collect())
+        .collect();
+    VideoOutcome::new(frames, fps, output)
+}
+
+fn into_outcome(method: &str, (boxes, image): ScriptResult) -> Outcome {
+    Outcome {
+        detections: boxes.into_iter().map(|b| Detection::new(method, b)).collect(),
+        image: image.as_bytes().to_vec(),
+    }
+}
+
+/// Maps the exceptions raised by the scripts, and failed imports, to a [`DetectError`].
+///
+/// `inputs` are the images given to the script, `UnreadableImage` holds the index of the one that failed.
+fn script_error(py: Python, err: PyErr, inputs: &[&Path]) -> DetectError {
+    let value = err.value(py);
+    let detail = || value.str().map(|s| s.to_string()).unwrap_or_default();
+
+    match err.get_type(py).name().unwrap_or_default() {
+        "UnreadableImage" => {
+            let input = value.getattr("args")
+                .and_then(|args| args.extract::<(usize,)>())
+                .ok()
+                .and_then(|(index,)| inputs.get(index));
+            match input {
+                Some(input) => DetectError::UnreadableImage(input.display().to_string()),
+                None => DetectError::UnreadableImage(detail()),
+            }
+        }
+        "UnreadableVideo" => DetectError::UnreadableVideo(detail()),
+        "MissingCascade" => DetectError::MissingCascade(detail()),
+        "EncodeFailure" => DetectError::EncodeFailure(detail()),
+        "WriteFailure" => DetectError::WriteFailure(detail()),
+        "SizeMismatch" => match value.getattr("args").and_then(|args| args.extract()) {
+            Ok((w1, h1, w2, h2)) => DetectError::SizeMismatch { first: (w1, h1), second: (w2, h2) },
+            Err(_) => DetectError::Other(anyhow::anyhow!("The images have different sizes")),
+        },
+        "ModuleNotFoundError" | "ImportError" => {
+            let module: String = value.getattr("name")
+                .and_then(|name| name.extract())
+                .unwrap_or_default();
+            match module.as_str() {
+                "cv2" | "numpy" => DetectError::MissingModule(module),
+                _ => DetectError::PythonUnavailable(detail()),
+            }
+        }
+        name => {
+            // Keep the traceback as the cause, the UI shows it under the details
+            let traceback = err.traceback(py)
+                .and_then(|traceback| traceback.format().ok())
+                .unwrap_or_default();
+            let err = anyhow::anyhow!("{}", traceback.trim_end()).context(format!("{name}: {}", detail()));
+            DetectError::Other(err)
+        }
+    }
+}
+
+/// The scripts, compiled on their first use and kept for the rest of the process.
+static COMMON: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+static DIFFCON: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+static HAAR: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+
+fn script<'py>(py: Python<'py>, cell: &'static GILOnceCell<Py<PyModule>>, code: &str, file: &str, name: &str) -> PyResult<&'py PyModule> {
+    if let Some(module) = cell.get(py) {
+        return Ok(module.as_ref(py));
+    }
+
+    let module = PyModule::from_code(py, code, file, name)?;
+    // Another thread may have compiled it while `from_code` released the GIL, either module works
+    let _ = cell.set(py, module.into());
+    Ok(module)
+}
+
+/// Helpers both scripts share, registered in `sys.modules` so they can `import common`.
+fn common(py: Python<'_>) -> PyResult<&PyModule> {
+    let compiled = COMMON.get(py).is_some();
+    let module = script(py, &COMMON,
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/common.py")),
+        "common.py",
+        "common"
+    )?;
+    if !compiled {
+        py.import("sys")?.getattr("modules")?.set_item("common", module)?;
+    }
+    Ok(module)
+}
+
+fn diffcon(py: Python<'_>) -> PyResult<&PyModule> {
+    common(py)?;
+    script(py, &DIFFCON,
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/diffcon.py")),
+        "diffcon.py",
+        "diffcon"
+    )
+}
+
+/// Regions as `(include, corners)` pairs, the corners rounded to whole pixels.
+fn regions_py(py: Python<'_>, regions: &[Region]) -> PyObject {
+    regions.iter()
+        .map(|region| (region.kind == RegionKind::Include, region.pixels()))
+        .collect::<Vec<_>>()
+        .into_py(py)
+}
+
+fn diff_kwargs<'py>(py: Python<'py>, params: &DiffParams, regions: &[Region]) -> &'py PyDict {
+    [
+        ("work_size", params.work_size.into_py(py)),
+        ("blur", params.blur.into_py(py)),
+        ("threshold", params.threshold.into_py(py)),
+        ("kernel", params.kernel.into_py(py)),
+        ("dilate_iterations", params.dilate_iterations.into_py(py)),
+        ("regions", regions_py(py, regions)),
+    ].into_py_dict(py)
+}
+
+fn run_diff<'py>(py: Python<'py>, img1: &[u8], img2: &[u8], ext: &str, params: &DiffParams, regions: &[Region]) -> PyResult<ScriptResult<'py>> {
+    diffcon(py)?.getattr("calculare_diff")?
+        .call((PyBytes::new(py, img1), PyBytes::new(py, img2), ext), Some(diff_kwargs(py, params, regions)))?
+        .extract()
+}
+
+pub fn diff_n_conn(img1: &Path, img2: &Path, ext: &str, params: &DiffParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    let (bytes1, bytes2) = (load(img1)?, load(img2)?);
+
+    let outcome = Python::with_gil(|py| {
+        run_diff(py, &bytes1, &bytes2, ext, params, regions)
+            .map(|result| into_outcome("diff", result))
+            .map_err(|err| script_error(py, err, &[img1, img2]))
+    })?;
+
+    Ok(outcome)
+}
+
+fn haar(py: Python<'_>) -> PyResult<&PyModule> {
+    common(py)?;
+    script(py, &HAAR,
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/python/haar.py")),
+        "haar.py",
+        "haar"
+    )
+}
+
+fn haar_kwargs<'py>(py: Python<'py>, params: &HaarParams, regions: &[Region]) -> &'py PyDict {
+    [
+        ("scale_factor", params.scale_factor.into_py(py)),
+        ("min_neighbors", params.min_neighbors.into_py(py)),
+        ("min_size", params.min_size.into_py(py)),
+        ("max_size", params.max_size.into_py(py)),
+        ("blur", params.blur.into_py(py)),
+        ("dilate", params.dilate.into_py(py)),
+        ("close", params.close.into_py(py)),
+        ("regions", regions_py(py, regions)),
+    ].into_py_dict(py)
+}
+
+fn run_haar<'py>(py: Python<'py>, img: &[u8], ext: &str, xml: &str, params: &HaarParams, regions: &[Region]) -> PyResult<ScriptResult<'py>> {
+    haar(py)?.getattr("haar_cascade")?
+        .call((PyBytes::new(py, img), ext, xml), Some(haar_kwargs(py, params, regions)))?
+        .extract()
+}
+
+pub fn haar_cascade(img: &Path, ext: &str, xml: &Path, params: &HaarParams, regions: &[Region]) -> Result<Outcome> {
+    params.validate()?;
+    // `CascadeClassifier` only takes UTF-8 paths
+    let xml = xml.to_str()
+        .ok_or_else(|| DetectError::MissingCascade(format!("{} (the path is not valid UTF-8)", xml.display())))?;
+
+    let bytes = load(img)?;
+
+    let outcome = Python::with_gil(|py| {
+        run_haar(py, &bytes, ext, xml, params, regions)
+            .map(|result| into_outcome("haar", result))
+            .map_err(|err| script_error(py, err, &[img]))
+    })?;
+
+    Ok(outcome)
+}
+
+/// `cv2.VideoCapture` and `cv2.VideoWriter` only take UTF-8 paths.
+fn video_paths<'a>(video: &'a Path, output: &'a Path) -> Result<(&'a str, &'a str), DetectError> {
+    let video_str = video.to_str()
+        .ok_or_else(|| DetectError::UnreadableVideo(format!("{} (the path is not valid UTF-8)", video.display())))?;
+    let output_str = output.to_str()
+        .ok_or_else(|| DetectError::WriteFailure(format!("{} (the path is not valid UTF-8)", output.display())))?;
+    Ok((video_str, output_str))
+}
+
+pub fn haar_video(video: &Path, output: &Path, fourcc: &str, xml: &Path, params: &HaarParams, regions: &[Region]) -> Result<VideoOutcome> {
+    params.validate()?;
+    let (video_str, output_str) = video_paths(video, output)?;
+    let xml = xml.to_str()
+        .ok_or_else(|| DetectError::MissingCascade(format!("{} (the path is not valid UTF-8)", xml.display())))?;
+
+    let outcome = Python::with_gil(|py| {
+        haar(py)
+            .and_then(|script| script.getattr("haar_video")?
+                .call((video_str, output_str, fourcc, xml), Some(haar_kwargs(py, params, regions)))?
+                .extract())
+            .map(|result| into_video_outcome("haar", result, output))
+            .map_err(|err| script_error(py, err, &[video]))
+    })?;
+
+    Ok(outcome)
+}
+
+pub fn first_frame(video: &Path) -> Result<Vec<u8>> {
+    let video_str = video.to_str()
+        .ok_or_else(|| DetectError::UnreadableVideo(format!("{} (the path is not valid UTF-8)", video.display())))?;
+
+    let frame = Python::with_gil(|py| {
+        haar(py)
+            .and_then(|script| script.getattr("first_frame")?.call1((video_str,))?.extract::<&PyBytes>())
+            .map(|frame| frame.as_bytes().to_vec())
+            .map_err(|err| script_error(py, err, &[video]))
+    })?;
+
+    Ok(frame)
+}
+
+pub fn diff_video(video: &Path, reference: Option<&Path>, output: &Path, fourcc: &str, params: &DiffParams, regions: &[Region]) -> Result<VideoOutcome> {
+    params.validate()?;
+    let (video_str, output_str) = video_paths(video, output)?;
+    let reference_bytes = reference.map(load).transpose()?;
+
+    let outcome = Python::with_gil(|py| {
+        let reference_py = reference_bytes.as_deref().map(|bytes| PyBytes::new(py, bytes));
+        diffcon(py)
+            .and_then(|script| script.getattr("diff_video")?
+                .call((video_str, reference_py, output_str, fourcc), Some(diff_kwargs(py, params, regions)))?
+                .extract())
+            .map(|result| into_video_outcome("diff", result, output))
+            .map_err(|err| script_error(py, err, &[video, reference.unwrap_or(video)]))
+    })?;
+
+    Ok(outcome)
+}
+
+pub fn mog2_background(frames: &[&Path]) -> Result<Vec<u8>> {
+    let bytes = frames.iter().map(|frame| load(frame)).collect::<Result<Vec<_>, _>>()?;
+
+    let background = Python::with_gil(|py| {
+        let frames_py: Vec<&PyBytes> = bytes.iter().map(|bytes| PyBytes::new(py, bytes)).collect();
+        diffcon(py)
+            .and_then(|script| script.getattr("mog2_background")?.call1((frames_py,))?.extract::<&PyBytes>())
+            .map(|background| background.as_bytes().to_vec())
+            .map_err(|err| script_error(py, err, frames))
+    })?;
+
+    Ok(background)
+}
+
+/// Reports the interpreter pyo3 linked against and the modules the scripts import.
+pub fn diagnose() -> Vec<Check> {
+    Python::with_gil(|py| {
+        let sys = py.import("sys");
+        let attr = |name: &str| -> Result<String, String> {
+            match &sys {
+                Ok(sys) => sys.getattr(name).and_then(|value| value.extract()).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            }
+        };
+        let module_version = |name: &str| -> Result<String, String> {
+            py.import(name)
+                .and_then(|module| module.getattr("__version__")?.extract::<String>())
+                .map_err(|err| err.to_string())
+        };
+
+        vec![
+            Check::new(
+                "Python version",
+                attr("version").map(|version| version.split_whitespace().next().unwrap_or_default().to_owned()),
+                "Install Python 3 and make sure its library can be found",
+            ),
+            Check::new(
+                "Python interpreter",
+                attr("executable"),
+                "Install Python 3 and add it to the PATH",
+            ),
+            Check::new(
+                "cv2",
+                module_version("cv2"),
+                "Run `python -m pip install opencv-python` with the interpreter above",
+            ),
+            Check::new(
+                "numpy",
+                module_version("numpy"),
+                "Run `python -m pip install numpy` with the interpreter above",
+            ),
+        ]
+    })
+}
@@ -0,0 +1,273 @@
+//! Regions of interest, polygons that restrict the methods to part of the image.
+//!
+//! Pixels outside the include regions, or inside an exclude region, are blacked out before
+//! detecting, so cars on an adjacent road or noise in the sky aren't counted. Without include
+//! regions the whole image is included.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{video::VideoOutcome, Detection};
+
+/// Whether a region keeps or removes what it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionKind {
+    Include,
+    Exclude,
+}
+
+impl RegionKind {
+    pub const ALL: &'static [RegionKind] = &[RegionKind::Include, RegionKind::Exclude];
+
+    /// Short unique identifier, used in the JSON files.
+    pub fn id(self) -> &'static str {
+        match self {
+            RegionKind::Include => "include",
+            RegionKind::Exclude => "exclude",
+        }
+    }
+
+    /// Name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Include => "Include",
+            RegionKind::Exclude => "Exclude",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        RegionKind::ALL.iter().copied().find(|kind| kind.id() == id)
+    }
+}
+
+/// Polygon drawn over the image, in pixels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    /// Name the counts of the region are reported under.
+    pub name: String,
+    pub kind: RegionKind,
+    /// Corners of the polygon, in order, the last one is joined to the first.
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Region {
+    pub fn new(name: impl Into<String>, kind: RegionKind, points: Vec<(f32, f32)>) -> Self {
+        Region { name: name.into(), kind, points }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.points.len() < 3 {
+            anyhow::bail!("The region {} needs at least 3 corners", self.name);
+        }
+        if self.points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            anyhow::bail!("The corners of the region {} must be numbers", self.name);
+        }
+        Ok(())
+    }
+
+    /// Whether the point is inside the polygon, by the even-odd rule.
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        let mut inside = false;
+        let mut previous = match self.points.last() {
+            Some(&last) => last,
+            None => return false,
+        };
+        for &(px, py) in &self.points {
+            let (qx, qy) = previous;
+            if (py > y) != (qy > y) && x < (qx - px) * (y - py) / (qy - py) + px {
+                inside = !inside;
+            }
+            previous = (px, py);
+        }
+        inside
+    }
+
+    /// Corners rounded to whole pixels, as the OpenCV backends draw them.
+    pub fn pixels(&self) -> Vec<(i32, i32)> {
+        self.points.iter().map(|(x, y)| (x.round() as i32, y.round() as i32)).collect()
+    }
+}
+
+/// Fails if a region can't be drawn.
+pub fn validate(regions: &[Region]) -> Result<()> {
+    regions.iter().try_for_each(Region::validate)
+}
+
+/// Reads regions saved as a JSON list, like `[{"name": "Road", "kind": "include", "points": [[0, 300], ...]}]`.
+pub fn load(path: &Path) -> Result<Vec<Region>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read the regions {}", path.display()))?;
+    let regions: Vec<Region> = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a list of regions", path.display()))?;
+    validate(&regions)?;
+    Ok(regions)
+}
+
+pub fn save(regions: &[Region], path: &Path) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(regions)?)
+        .with_context(|| format!("Could not save the regions to {}", path.display()))
+}
+
+/// Whether the point is kept by the regions, every point is kept without regions.
+pub fn keeps(regions: &[Region], point: (f32, f32)) -> bool {
+    let mut includes = regions.iter().filter(|region| region.kind == RegionKind::Include).peekable();
+    let included = includes.peek().is_none() || includes.any(|region| region.contains(point));
+    included && !regions.iter().any(|region| region.kind == RegionKind::Exclude && region.contains(point))
+}
+
+/// Pixels kept by the regions, row by row, 255 where kept and 0 elsewhere.
+pub fn mask(regions: &[Region], width: u32, height: u32) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let centre = (x as f32 + 0.5, y as f32 + 0.5);
+            mask.push(if keeps(regions, centre) { 255 } else { 0 });
+        }
+    }
+    mask
+}
+
+/// Cars found in an include region.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Include regions the detections are counted in.
+pub fn includes(regions: &[Region]) -> impl Iterator<Item = &Region> {
+    regions.iter().filter(|region| region.kind == RegionKind::Include)
+}
+
+fn centre(detection: &Detection) -> (f32, f32) {
+    (detection.x as f32 + detection.w as f32 / 2., detection.y as f32 + detection.h as f32 / 2.)
+}
+
+/// Detections of each include region, by the centre of their box.
+///
+/// Regions may overlap, a car in both is counted in each. Cars in an exclude region aren't
+/// counted, as the mask removes them.
+pub fn counts(regions: &[Region], detections: &[Detection]) -> Vec<RegionCount> {
+    let excluded = |point| regions.iter().any(|region| region.kind == RegionKind::Exclude && region.contains(point));
+    let kept: Vec<(f32, f32)> = detections.iter().map(centre).filter(|&point| !excluded(point)).collect();
+    includes(regions).map(|region| RegionCount {
+        name: region.name.clone(),
+        count: kept.iter().filter(|&&point| region.contains(point)).count(),
+    }).collect()
+}
+
+/// The count of every frame as CSV, like [`VideoOutcome::to_csv`] with a column per include region.
+pub fn to_csv(regions: &[Region], outcome: &VideoOutcome) -> String {
+    let mut csv = "frame,seconds,count".to_owned();
+    for region in includes(regions) {
+        // Names are free text, quote them
+        csv.push_str(&format!(",\"{}\"", region.name.replace('"', "\"\"")));
+    }
+    csv.push('\n');
+
+    for (i, detections) in outcome.frames.iter().enumerate() {
+        csv.push_str(&format!("{i},{:.3},{}", outcome.time(i), detections.len()));
+        for count in counts(regions, detections) {
+            csv.push_str(&format!(",{}", count.count));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str, kind: RegionKind, (x, y): (f32, f32), size: f32) -> Region {
+        Region::new(name, kind, vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        // L shape, the top right corner is outside
+        let shape = Region::new("L", RegionKind::Include, vec![(0., 0.), (10., 0.), (10., 20.), (20., 20.), (20., 30.), (0., 30.)]);
+        assert!(shape.contains((5., 5.)));
+        assert!(shape.contains((15., 25.)));
+        assert!(!shape.contains((15., 10.)));
+        assert!(!shape.contains((-1., 5.)));
+        assert!(!shape.contains((5., 31.)));
+        assert!(!Region::new("Empty", RegionKind::Include, vec![]).contains((0., 0.)));
+    }
+
+    #[test]
+    fn includes_and_excludes() {
+        assert!(keeps(&[], (1000., 1000.)));
+
+        let road = square("Road", RegionKind::Include, (0., 0.), 10.);
+        let sign = square("Sign", RegionKind::Exclude, (2., 2.), 3.);
+        let regions = [road.clone(), sign];
+        assert!(keeps(&regions, (8., 8.)));
+        assert!(!keeps(&regions, (3., 3.)));
+        assert!(!keeps(&regions, (12., 8.)));
+
+        // Only excluding keeps the rest of the image
+        assert!(keeps(&regions[1..], (12., 8.)));
+        assert!(!keeps(&regions[1..], (3., 3.)));
+
+        // A point in any of the include regions is kept
+        let other = square("Other", RegionKind::Include, (20., 0.), 10.);
+        assert!(keeps(&[road, other], (25., 5.)));
+    }
+
+    #[test]
+    fn mask_by_pixel_centres() {
+        let regions = [square("Road", RegionKind::Include, (1., 0.), 2.), square("Sign", RegionKind::Exclude, (2., 1.), 1.)];
+        assert_eq!(mask(&regions, 4, 2), vec![0, 255, 255, 0, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn counts_by_centre() {
+        let regions = [
+            square("Left", RegionKind::Include, (0., 0.), 50.),
+            square("Both", RegionKind::Include, (25., 0.), 50.),
+        ];
+        // Centres at (10, 10), (40, 10) and (90, 10)
+        let detections = [
+            Detection::new("test", (0, 5, 20, 10, 1.)),
+            Detection::new("test", (30, 5, 20, 10, 1.)),
+            Detection::new("test", (80, 5, 20, 10, 1.)),
+        ];
+        let count = |name: &str, count| RegionCount { name: name.to_owned(), count };
+        assert_eq!(counts(&regions, &detections), vec![count("Left", 2), count("Both", 1)]);
+
+        let outcome = VideoOutcome::new(vec![detections.to_vec(), vec![]], 2., Path::new("out.mp4"));
+        let regions = [Region { name: "The \"left\"".to_owned(), ..regions[0].clone() }];
+        assert_eq!(to_csv(&regions, &outcome), "frame,seconds,count,\"The \"\"left\"\"\"\n0,0.000,3,2\n1,0.500,0,0\n");
+    }
+
+    #[test]
+    fn excluded_cars_are_not_counted() {
+        // The sign covers part of the road, the car in front of it isn't counted
+        let regions = [
+            square("Road", RegionKind::Include, (0., 0.), 100.),
+            square("Sign", RegionKind::Exclude, (30., 0.), 20.),
+        ];
+        let detections = [
+            Detection::new("test", (0, 5, 20, 10, 1.)),
+            Detection::new("test", (30, 5, 20, 10, 1.)),
+            Detection::new("test", (80, 5, 20, 10, 1.)),
+        ];
+        assert_eq!(counts(&regions, &detections), vec![RegionCount { name: "Road".to_owned(), count: 2 }]);
+
+        let outcome = VideoOutcome::new(vec![detections.to_vec()], 1., Path::new("out.mp4"));
+        assert_eq!(to_csv(&regions, &outcome), "frame,seconds,count,\"Road\"\n0,0.000,3,2\n");
+    }
+
+    #[test]
+    fn rejects_bad_regions() {
+        assert!(validate(&[square("Road", RegionKind::Include, (0., 0.), 10.)]).is_ok());
+        assert!(Region::new("Line", RegionKind::Include, vec![(0., 0.), (1., 1.)]).validate().is_err());
+        assert!(Region::new("Nan", RegionKind::Exclude, vec![(0., 0.), (1., f32::NAN), (1., 0.)]).validate().is_err());
+        assert_eq!(RegionKind::from_id("exclude"), Some(RegionKind::Exclude));
+        assert_eq!(RegionKind::from_id("other"), None);
+    }
+}
@@ -0,0 +1,292 @@
+//! Following the cars found in consecutive frames, so each one is counted once.
+//!
+//! Detections are matched to the tracks of the previous frames greedily, first by the overlap
+//! of their boxes and then, for cars that moved further than their size, by the distance
+//! between their centres. Detections left unmatched start new tracks.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::Detection;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackerParams {
+    /// Smallest intersection over union of two boxes of the same car, from 0 to 1.
+    pub min_iou: f32,
+    /// Farthest the centre of a car moves between frames, relative to the diagonal of its box.
+    ///
+    /// Grows with each frame the car goes undetected, since it keeps moving meanwhile.
+    pub max_distance: f32,
+    /// Frames a track survives without detections before it ends.
+    pub max_missed: usize,
+    /// Detections a track needs to count as a car, shorter tracks are treated as noise.
+    pub min_hits: usize,
+}
+
+impl Default for TrackerParams {
+    fn default() -> Self {
+        TrackerParams { min_iou: 0.3, max_distance: 1., max_missed: 5, min_hits: 3 }
+    }
+}
+
+impl TrackerParams {
+    pub fn validate(&self) -> Result<()> {
+        if !(0. ..=1.).contains(&self.min_iou) {
+            anyhow::bail!("The minimum overlap must be between 0 and 1");
+        }
+        if self.max_distance < 0. {
+            anyhow::bail!("The maximum distance can't be negative");
+        }
+        if self.min_hits < 1 {
+            anyhow::bail!("A track needs at least one detection");
+        }
+        Ok(())
+    }
+}
+
+/// A car followed through several frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    /// Stable identifier, starting at 1.
+    pub id: usize,
+    /// First and last frame the car was detected in.
+    pub first: usize,
+    pub last: usize,
+    /// Frames the car was detected in.
+    pub hits: usize,
+}
+
+/// Track of every detection of a sequence of frames.
+#[derive(Clone, Debug, Default)]
+pub struct Tracking {
+    /// Id of the track of each detection, in the order of the detections of each frame.
+    pub ids: Vec<Vec<usize>>,
+    /// Every track, by id.
+    pub tracks: Vec<Track>,
+    /// Detections a track needs to count as a car.
+    pub min_hits: usize,
+}
+
+impl Tracking {
+    /// Whether the track is long enough to be a car.
+    pub fn confirmed(&self, id: usize) -> bool {
+        id.checked_sub(1).and_then(|index| self.tracks.get(index)).map(|track| track.hits >= self.min_hits).unwrap_or(false)
+    }
+
+    /// Amount of different cars seen.
+    pub fn unique(&self) -> usize {
+        self.tracks.iter().filter(|track| track.hits >= self.min_hits).count()
+    }
+
+    /// Confirmed detections of `frames` as CSV, with a `frame,track,x,y,w,h` header.
+    pub fn to_csv(&self, frames: &[Vec<Detection>]) -> String {
+        let mut csv = "frame,track,x,y,w,h\n".to_owned();
+        for (i, (detections, ids)) in frames.iter().zip(&self.ids).enumerate() {
+            for (d, &id) in detections.iter().zip(ids) {
+                if self.confirmed(id) {
+                    csv.push_str(&format!("{i},{id},{},{},{},{}\n", d.x, d.y, d.w, d.h));
+                }
+            }
+        }
+        csv
+    }
+}
+
+/// Box of a track that is still being followed.
+struct Active {
+    id: usize,
+    last_box: (f32, f32, f32, f32),
+    missed: usize,
+}
+
+/// Assigns stable ids to the detections of consecutive frames.
+pub struct Tracker {
+    params: TrackerParams,
+    active: Vec<Active>,
+    tracks: Vec<Track>,
+    ids: Vec<Vec<usize>>,
+}
+
+impl Tracker {
+    pub fn new(params: TrackerParams) -> Self {
+        Tracker { params, active: vec![], tracks: vec![], ids: vec![] }
+    }
+
+    /// Matches the detections of the next frame, returns the id of the track of each one.
+    pub fn update(&mut self, detections: &[Detection]) -> &[usize] {
+        let frame = self.ids.len();
+        let boxes: Vec<_> = detections.iter().map(|d| (d.x as f32, d.y as f32, d.w as f32, d.h as f32)).collect();
+
+        // Every close enough pair, best first, overlapping pairs before distant ones
+        let mut candidates = vec![];
+        for (t, track) in self.active.iter().enumerate() {
+            for (d, detection) in boxes.iter().enumerate() {
+                let overlap = iou(track.last_box, *detection);
+                if overlap > 0. && overlap >= self.params.min_iou {
+                    candidates.push((0, -overlap, t, d));
+                    continue;
+                }
+                let distance = centre_distance(track.last_box, *detection) / diagonal(track.last_box).max(1.);
+                if distance <= self.params.max_distance * (track.missed + 1) as f32 {
+                    candidates.push((1, distance, t, d));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+        let mut assigned: Vec<Option<usize>> = vec![None; boxes.len()];
+        let mut matched = vec![false; self.active.len()];
+        for (_, _, t, d) in candidates {
+            if matched[t] || assigned[d].is_some() {
+                continue;
+            }
+            matched[t] = true;
+            assigned[d] = Some(t);
+        }
+
+        for (t, track) in self.active.iter_mut().enumerate() {
+            if !matched[t] {
+                track.missed += 1;
+            }
+        }
+
+        let mut ids = Vec::with_capacity(boxes.len());
+        for (d, detection) in boxes.into_iter().enumerate() {
+            let id = match assigned[d] {
+                Some(t) => {
+                    let active = &mut self.active[t];
+                    active.last_box = detection;
+                    active.missed = 0;
+                    let track = &mut self.tracks[active.id - 1];
+                    track.last = frame;
+                    track.hits += 1;
+                    active.id
+                }
+                None => {
+                    let id = self.tracks.len() + 1;
+                    self.tracks.push(Track { id, first: frame, last: frame, hits: 1 });
+                    self.active.push(Active { id, last_box: detection, missed: 0 });
+                    id
+                }
+            };
+            ids.push(id);
+        }
+
+        let max_missed = self.params.max_missed;
+        self.active.retain(|track| track.missed <= max_missed);
+        self.ids.push(ids);
+        &self.ids[frame]
+    }
+
+    pub fn finish(self) -> Tracking {
+        Tracking { ids: self.ids, tracks: self.tracks, min_hits: self.params.min_hits }
+    }
+}
+
+/// Tracks the cars of every frame, in order.
+pub fn track(frames: &[Vec<Detection>], params: &TrackerParams) -> Result<Tracking> {
+    params.validate()?;
+    let mut tracker = Tracker::new(*params);
+    for detections in frames {
+        tracker.update(detections);
+    }
+    Ok(tracker.finish())
+}
+
+/// Intersection over union of two `(x, y, w, h)` boxes.
+fn iou(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+    let width = ((a.0 + a.2).min(b.0 + b.2) - a.0.max(b.0)).max(0.);
+    let height = ((a.1 + a.3).min(b.1 + b.3) - a.1.max(b.1)).max(0.);
+    let intersection = width * height;
+    let union = a.2 * a.3 + b.2 * b.3 - intersection;
+    if union <= 0. { 0. } else { intersection / union }
+}
+
+fn centre_distance(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+    let (ax, ay) = (a.0 + a.2 / 2., a.1 + a.3 / 2.);
+    let (bx, by) = (b.0 + b.2 / 2., b.1 + b.3 / 2.);
+    (ax - bx).hypot(ay - by)
+}
+
+fn diagonal(b: (f32, f32, f32, f32)) -> f32 {
+    b.2.hypot(b.3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car(x: i32, y: i32) -> Detection {
+        Detection::new("test", (x, y, 20, 10, 1.))
+    }
+
+    fn params(max_missed: usize, min_hits: usize) -> TrackerParams {
+        TrackerParams { max_missed, min_hits, ..TrackerParams::default() }
+    }
+
+    #[test]
+    fn iou_of_boxes() {
+        assert_eq!(iou((0., 0., 10., 10.), (0., 0., 10., 10.)), 1.);
+        assert_eq!(iou((0., 0., 10., 10.), (20., 0., 10., 10.)), 0.);
+        assert!((iou((0., 0., 10., 10.), (5., 0., 10., 10.)) - 1. / 3.).abs() < 1e-6);
+        assert_eq!(iou((0., 0., 0., 0.), (0., 0., 0., 0.)), 0.);
+    }
+
+    #[test]
+    fn follows_overlapping_boxes() {
+        // The cars are listed in another order in the second frame
+        let frames = vec![vec![car(0, 0), car(100, 0)], vec![car(102, 1), car(3, 0)]];
+        let tracking = track(&frames, &params(5, 1)).unwrap();
+        assert_eq!(tracking.ids, vec![vec![1, 2], vec![2, 1]]);
+        assert_eq!(tracking.unique(), 2);
+    }
+
+    #[test]
+    fn best_overlap_wins() {
+        let frames = vec![vec![car(0, 0)], vec![car(8, 0), car(2, 0)]];
+        let tracking = track(&frames, &params(5, 1)).unwrap();
+        assert_eq!(tracking.ids[1], vec![2, 1]);
+    }
+
+    #[test]
+    fn follows_fast_cars_by_distance() {
+        // Moves its whole width each frame, so the boxes never overlap
+        let frames: Vec<_> = (0..4).map(|i| vec![car(i * 20, 0)]).collect();
+        let tracking = track(&frames, &params(5, 1)).unwrap();
+        assert_eq!(tracking.ids, vec![vec![1]; 4]);
+
+        let strict = TrackerParams { max_distance: 0.5, ..params(5, 1) };
+        assert_eq!(track(&frames, &strict).unwrap().unique(), 4);
+    }
+
+    #[test]
+    fn short_tracks_are_noise() {
+        let frames = vec![vec![car(0, 0), car(200, 200)], vec![car(1, 0)], vec![car(2, 0)]];
+        let tracking = track(&frames, &params(5, 3)).unwrap();
+        assert_eq!(tracking.tracks.len(), 2);
+        assert_eq!(tracking.unique(), 1);
+        assert!(tracking.confirmed(1) && !tracking.confirmed(2) && !tracking.confirmed(3));
+        assert_eq!(tracking.to_csv(&frames), "frame,track,x,y,w,h\n0,1,0,0,20,10\n1,1,1,0,20,10\n2,1,2,0,20,10\n");
+
+        assert_eq!(track(&frames, &params(5, 1)).unwrap().unique(), 2);
+    }
+
+    #[test]
+    fn tracks_end_after_max_missed() {
+        // Hidden for two frames, then back where it was
+        let frames = vec![vec![car(0, 0)], vec![], vec![], vec![car(0, 0)]];
+        let kept = track(&frames, &params(2, 1)).unwrap();
+        assert_eq!(kept.ids[3], vec![1]);
+        assert_eq!(kept.tracks[0], Track { id: 1, first: 0, last: 3, hits: 2 });
+
+        let ended = track(&frames, &params(1, 1)).unwrap();
+        assert_eq!(ended.ids[3], vec![2]);
+        assert_eq!(ended.unique(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        assert!(track(&[], &TrackerParams { min_iou: 1.5, ..TrackerParams::default() }).is_err());
+        assert!(track(&[], &TrackerParams { min_hits: 0, ..TrackerParams::default() }).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Running a [`Detector`](super::Detector) on every frame of a video.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::{error::DetectError, Backend, Detection};
+
+/// Extensions of the videos that can be read, any format FFmpeg supports usually works too.
+pub const EXTENSIONS: &[&str] = &["mp4", "avi", "mov", "mkv", "m4v"];
+
+/// Formats the annotated video can be written in.
+pub const OUTPUT_FORMATS: &[&str] = &["mp4", "avi"];
+
+/// Whether the file looks like a video, by its extension.
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Codec of the annotated video, picked by the extension of `output`.
+pub fn fourcc(output: &Path) -> Result<&'static str, DetectError> {
+    let ext = output.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp4" => Ok("mp4v"),
+        "avi" => Ok("MJPG"),
+        _ => Err(DetectError::UnsupportedVideoFormat(ext)),
+    }
+}
+
+/// Default path of the annotated video of `video`, an MP4 in a new folder of the history.
+///
+/// Keep the run with [`history::save_video`](super::history::save_video), or remove the folder
+/// with [`history::discard_video`](super::history::discard_video) if the run fails.
+pub fn default_output(video: &Path) -> Result<PathBuf> {
+    let stem = video.file_stem().unwrap_or_default().to_string_lossy();
+    Ok(super::history::video_dir()?.join(format!("{stem}.mp4")))
+}
+
+/// First frame of `video` encoded as PNG, to draw on before running a method.
+pub fn first_frame(video: &Path, backend: Backend) -> Result<Vec<u8>, DetectError> {
+    let frame = match backend {
+        #[cfg(feature = "opencv-metal")]
+        Backend::Metal => super::metal::first_frame(video),
+        #[cfg(feature = "opencv-python")]
+        Backend::Python => super::python::first_frame(video),
+        #[cfg(feature = "native")]
+        Backend::Native => super::native::first_frame(video),
+    };
+    Ok(frame?)
+}
+
+/// What a [`Detector`](super::Detector) found in each frame of a video.
+#[derive(Clone, Debug)]
+pub struct VideoOutcome {
+    /// Detections of every frame, in order.
+    pub frames: Vec<Vec<Detection>>,
+    /// Frames per second of the video.
+    pub fps: f64,
+    /// Path of the video with the detections drawn on top.
+    pub output: PathBuf,
+}
+
+impl VideoOutcome {
+    pub fn new(frames: Vec<Vec<Detection>>, fps: f64, output: &Path) -> Self {
+        VideoOutcome { frames, fps, output: output.to_path_buf() }
+    }
+
+    /// Amount of cars found in each frame.
+    pub fn counts(&self) -> Vec<usize> {
+        self.frames.iter().map(|frame| frame.len()).collect()
+    }
+
+    /// Most cars found in a single frame.
+    pub fn max(&self) -> usize {
+        self.counts().into_iter().max().unwrap_or(0)
+    }
+
+    /// Average amount of cars per frame.
+    pub fn mean(&self) -> f64 {
+        if self.frames.is_empty() {
+            return 0.;
+        }
+        self.counts().iter().sum::<usize>() as f64 / self.frames.len() as f64
+    }
+
+    /// Time of a frame since the start of the video, in seconds.
+    pub fn time(&self, frame: usize) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    /// The count of every frame as CSV, with a `frame,seconds,count` header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "frame,seconds,count\n".to_owned();
+        for (i, count) in self.counts().into_iter().enumerate() {
+            csv.push_str(&format!("{i},{:.3},{count}\n", self.time(i)));
+        }
+        csv
+    }
+}
@@ -0,0 +1,56 @@
+import cv2 as cv
+import numpy as np
+
+class UnreadableImage(Exception):
+    pass
+
+class EncodeFailure(Exception):
+    pass
+
+class UnreadableVideo(Exception):
+    pass
+
+class WriteFailure(Exception):
+    pass
+
+def decode(data, index):
+    img = cv.imdecode(np.frombuffer(data, dtype=np.uint8), cv.IMREAD_COLOR) if data else None
+    if img is None:
+        raise UnreadableImage(index)
+    return img
+
+def encode(img, ext):
+    ok, encoded = cv.imencode(f".{ext}", img)
+    if not ok:
+        raise EncodeFailure(ext)
+    return encoded.tobytes()
+
+def masked(img, regions):
+    """Copy of `img` black outside the regions, given as `(include, corners)` pairs."""
+    if not regions:
+        return img
+    includes = [corners for include, corners in regions if include]
+    excludes = [corners for include, corners in regions if not include]
+    # Without include regions the whole image is included. Each polygon is filled on its own,
+    # `fillPoly` would leave holes where the polygons of a single call overlap
+    mask = np.zeros(img.shape[:2], np.uint8) if includes else np.full(img.shape[:2], 255, np.uint8)
+    for corners in includes:
+        cv.fillPoly(mask, [np.array(corners, np.int32)], 255)
+    for corners in excludes:
+        cv.fillPoly(mask, [np.array(corners, np.int32)], 0)
+    return cv.bitwise_and(img, img, mask=mask)
+
+def open_video(path):
+    capture = cv.VideoCapture(path)
+    if not capture.isOpened():
+        raise UnreadableVideo(path)
+    # Some containers don't store the frame rate
+    fps = capture.get(cv.CAP_PROP_FPS) or 25.0
+    return (capture, fps)
+
+def open_writer(path, fourcc, fps, frame):
+    height, width = frame.shape[:2]
+    writer = cv.VideoWriter(path, cv.VideoWriter_fourcc(*fourcc), fps, (width, height))
+    if not writer.isOpened():
+        raise WriteFailure(path)
+    return writer
@@ -1,48 +1,110 @@
+import math
+
 import cv2 as cv
 import numpy as np
 
-def calculare_diff(img1: str, img2: str, ext: str, out_dir: str):
-    img1 = cv.imread(rf"{img1}")
-    img2 = cv.imread(rf"{img2}")
+from common import UnreadableVideo, decode, encode, masked, open_video, open_writer
+
+class SizeMismatch(Exception):
+    pass
+
+def find_changes(img1, img2, work_size=500, blur=5, threshold=127, kernel=(8, 2), dilate_iterations=5, regions=()):
+    """Draws what changed between the images on `img1`, returns the boxes."""
+    if img1.shape[:2] != img2.shape[:2]:
+        raise SizeMismatch(img1.shape[1], img1.shape[0], img2.shape[1], img2.shape[0])
 
-    # Resizing the images to max 500 pixels
-    img1 = cv.resize(img1, (500, 500))
-    img2 = cv.resize(img2, (500, 500))
+    # Working copies with the longest side of `work_size` pixels, keeping the aspect ratio
+    height, width = img1.shape[:2]
+    scale = work_size / max(width, height)
+    size = (max(1, round(width * scale)), max(1, round(height * scale)))
+    img1_small = cv.resize(masked(img1, regions), size)
+    img2_small = cv.resize(masked(img2, regions), size)
 
     # Convert to grayscale and apply Gaussian blur
-    img1_gray = cv.cvtColor(img1, cv.COLOR_BGR2GRAY)
-    img1_blur = cv.GaussianBlur(img1_gray, (5, 5), 0)
+    img1_gray = cv.cvtColor(img1_small, cv.COLOR_BGR2GRAY)
+    img1_blur = cv.GaussianBlur(img1_gray, (blur, blur), 0)
 
-    img2_gray = cv.cvtColor(img2, cv.COLOR_BGR2GRAY)
-    img2_blur = cv.GaussianBlur(img2_gray, (5, 5), 0)
+    img2_gray = cv.cvtColor(img2_small, cv.COLOR_BGR2GRAY)
+    img2_blur = cv.GaussianBlur(img2_gray, (blur, blur), 0)
 
     # Binary thresholding of the images
-    ret, img1_thresh = cv.threshold(img1_blur, 127, 255, cv.THRESH_BINARY)
-    ret, img2_thresh = cv.threshold(img2_blur, 127, 255, cv.THRESH_BINARY)
+    ret, img1_thresh = cv.threshold(img1_blur, threshold, 255, cv.THRESH_BINARY)
+    ret, img2_thresh = cv.threshold(img2_blur, threshold, 255, cv.THRESH_BINARY)
 
     # Get difference between images
     img_diff = cv.absdiff(img1_thresh, img2_thresh)
 
     # Reduce noise
-    kernel = np.ones((8, 2), np.uint8)
+    kernel = np.ones(tuple(kernel), np.uint8)
     img_diff = cv.morphologyEx(img_diff, cv.MORPH_OPEN, kernel)
 
     # Dilate the image
-    img_diff = cv.dilate(img_diff, kernel, iterations=5)
+    img_diff = cv.dilate(img_diff, kernel, iterations=dilate_iterations)
 
     # Show connected components in the image
     num_labels, labels, stats, centroids = cv.connectedComponentsWithStats(img_diff)
 
-    # Draw the bounding boxes of 
+    # Draw the bounding boxes of the components on the original image, label 0 is the background
+    thickness = max(2, round(2 / scale))
+    boxes = []
     for i in range(1, num_labels):
-        x = stats[i, cv.CC_STAT_LEFT]
-        y = stats[i, cv.CC_STAT_TOP]
-        w = stats[i, cv.CC_STAT_WIDTH]
-        h = stats[i, cv.CC_STAT_HEIGHT]
-        cv.rectangle(img1, (x, y), (x + w, y + h), (0, 255, 0), 2)
-
-    path = rf"{out_dir}/img.{ext}"
-    if cv.imwrite(path, img1):
-        return (num_labels - 1, rf"{path}")
-    else:
-        return (num_labels - 1, "ERROR")
\ No newline at end of file
+        x = int(stats[i, cv.CC_STAT_LEFT])
+        y = int(stats[i, cv.CC_STAT_TOP])
+        w = int(stats[i, cv.CC_STAT_WIDTH])
+        h = int(stats[i, cv.CC_STAT_HEIGHT])
+        area = int(stats[i, cv.CC_STAT_AREA])
+
+        x0, y0 = int(x / scale), int(y / scale)
+        x1 = min(width, math.ceil((x + w) / scale))
+        y1 = min(height, math.ceil((y + h) / scale))
+        boxes.append((x0, y0, x1 - x0, y1 - y0, area / (w * h)))
+        cv.rectangle(img1, (x0, y0), (x1, y1), (0, 255, 0), thickness)
+
+    return boxes
+
+def calculare_diff(img1: bytes, img2: bytes, ext: str, **params):
+    img1 = decode(img1, 0)
+    img2 = decode(img2, 1)
+    boxes = find_changes(img1, img2, **params)
+    return (boxes, encode(img1, ext))
+
+def diff_video(video: str, reference, output: str, fourcc: str, **params):
+    """Compares every frame with `reference`, or with the first frame if it's `None`."""
+    if reference is not None:
+        reference = decode(reference, 1)
+    capture, fps = open_video(video)
+    writer = None
+    frames = []
+    try:
+        while True:
+            ok, frame = capture.read()
+            if not ok:
+                break
+            if writer is None:
+                writer = open_writer(output, fourcc, fps, frame)
+            if reference is None:
+                reference = frame.copy()
+            frames.append(find_changes(frame, reference, **params))
+            writer.write(frame)
+    finally:
+        capture.release()
+        if writer is not None:
+            writer.release()
+
+    if not frames:
+        raise UnreadableVideo(video)
+    return (frames, fps)
+
+def mog2_background(frames: list):
+    """Background of the frames, as PNG, learned by OpenCV's MOG2 subtractor."""
+    subtractor = cv.createBackgroundSubtractorMOG2(history=len(frames), detectShadows=False)
+    first = None
+    for i, data in enumerate(frames):
+        frame = decode(data, i)
+        if first is None:
+            first = frame
+        elif frame.shape[:2] != first.shape[:2]:
+            raise SizeMismatch(first.shape[1], first.shape[0], frame.shape[1], frame.shape[0])
+        subtractor.apply(frame)
+
+    return encode(subtractor.getBackgroundImage(), "png")
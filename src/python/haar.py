@@ -1,24 +1,79 @@
+import os
+
 import cv2 as cv
 import numpy as np
 
-def haar_cascade(ruta: str, ext: str, out_dir: str, xml: str):
-    img = cv.imread(ruta)
-    img_arr = np.array(img)
-    img = img[:,:,::-1]
-    imgray = cv.cvtColor(img, cv.COLOR_RGB2GRAY)
-    blur = cv.GaussianBlur(imgray,(5,5),0)
-    dilated = cv.dilate(blur,np.ones((3,3)))
-    kernel = cv.getStructuringElement(cv.MORPH_ELLIPSE, (2, 2))
+from common import UnreadableVideo, decode, encode, masked, open_video, open_writer
+
+class MissingCascade(Exception):
+    pass
+
+# Cascades already loaded, by path, along with the modification time of the file
+cascades = {}
+
+def load_cascade(xml):
+    modified = os.path.getmtime(xml) if os.path.isfile(xml) else None
+    cached = cascades.get(xml)
+    if cached is not None and modified is not None and cached[0] == modified:
+        return cached[1]
+
+    cascade = cv.CascadeClassifier(xml)
+    if cascade.empty():
+        raise MissingCascade(xml)
+    cascades[xml] = (modified, cascade)
+    return cascade
+
+def find_cars(img, car_cascade, scale_factor=1.1, min_neighbors=1, min_size=(0, 0), max_size=(0, 0),
+              blur=5, dilate=3, close=2, regions=()):
+    """Draws the cars found on `img`, returns their boxes."""
+    imgray = cv.cvtColor(masked(img, regions), cv.COLOR_BGR2GRAY)
+    blurred = cv.GaussianBlur(imgray,(blur,blur),0)
+    dilated = cv.dilate(blurred,np.ones((dilate,dilate)))
+    kernel = cv.getStructuringElement(cv.MORPH_ELLIPSE, (close, close))
     closing = cv.morphologyEx(dilated, cv.MORPH_CLOSE, kernel)
-    car_cascade = cv.CascadeClassifier(xml)
-    cars = car_cascade.detectMultiScale(closing, 1.1, 1)
-    cnt = 0
-    for (x,y,w,h) in cars:
-        cv.rectangle(img_arr, (x, y), (x + w, y + h), (255, 0, 0), 2)
-        cnt += 1
-    
-    path = rf"{out_dir}/img.{ext}"
-    if cv.imwrite(path, img_arr):
-        return (cnt, rf"{path}")
-    else:
-        return (cnt, "ERROR")
\ No newline at end of file
+    cars, neighbours = car_cascade.detectMultiScale2(
+        closing, scaleFactor=scale_factor, minNeighbors=min_neighbors,
+        minSize=tuple(min_size), maxSize=tuple(max_size))
+    boxes = []
+    for (x,y,w,h), n in zip(cars, neighbours):
+        cv.rectangle(img, (x, y), (x + w, y + h), (255, 0, 0), 2)
+        boxes.append((int(x), int(y), int(w), int(h), float(n)))
+    return boxes
+
+def haar_cascade(img: bytes, ext: str, xml: str, **params):
+    img = decode(img, 0)
+    boxes = find_cars(img, load_cascade(xml), **params)
+    return (boxes, encode(img, ext))
+
+def haar_video(video: str, output: str, fourcc: str, xml: str, **params):
+    car_cascade = load_cascade(xml)
+    capture, fps = open_video(video)
+    writer = None
+    frames = []
+    try:
+        while True:
+            ok, frame = capture.read()
+            if not ok:
+                break
+            if writer is None:
+                writer = open_writer(output, fourcc, fps, frame)
+            frames.append(find_cars(frame, car_cascade, **params))
+            writer.write(frame)
+    finally:
+        capture.release()
+        if writer is not None:
+            writer.release()
+
+    if not frames:
+        raise UnreadableVideo(video)
+    return (frames, fps)
+
+def first_frame(video: str):
+    capture, fps = open_video(video)
+    try:
+        ok, frame = capture.read()
+    finally:
+        capture.release()
+    if not ok:
+        raise UnreadableVideo(video)
+    return encode(frame, "png")
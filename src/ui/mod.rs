@@ -0,0 +1,2279 @@
+use std::{io::{Read, BufWriter}, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc}};
+
+use dioxus::{prelude::*, events::onchange};
+use dioxus_desktop::Config;
+use dioxus_router::*;
+use anyhow::Result;
+
+use crate::detect::{self, background::{self, BackgroundParams, Estimator}, batch, cascades::Library, doctor::{self, Check}, export, history, line::{self, CountingLine}, roi::{self, RegionCount, RegionKind}, track::{self, TrackerParams}, video, Backend, DetectError, DiffConnect, DiffParams, Detector, HaarCascade, HaarParams, Input, Kind, Outcome, Region, VideoOutcome};
+
+mod icons;
+use icons::{MoonIcon, SunIcon};
+
+#[inline_props]
+pub fn ItemStickyMenu<'a>(cx: Scope, to: &'a str, children: Element<'a>) -> Element {
+    cx.render(rsx! {
+        Link {
+            class: "cursor-pointer hover:text-gray-200",
+            to: "{to}",
+            children
+        }
+    })
+}
+
+pub fn Sticky(cx: Scope) -> Element {
+    const SCRIPT: &str = r#"
+    const html = document.getElementsByTagName('html')[0];
+    if (localStorage.theme === 'dark') {{
+        document.getElementById("t_color").content = "rgb(243 244 246 / var(--tw-bg-opacity))"
+        html.classList.remove('dark');
+        localStorage.theme = 'light'
+    }} else {{
+        document.getElementById("t_color").content = "rgb(17 24 39 / 0.9)"
+        html.classList.add('dark');
+        localStorage.theme = 'dark'
+    }}
+    "#;
+    cx.render(rsx! {
+        nav {
+            style: "z-index: 10;",
+            class:"sticky top-0",
+            div {
+                class: "glass bg-titlebar p-2 backdrop-filter backdrop-blur-xl",
+                div {
+                    class:"flex items-center justify-center text-sm space-x-10 text-white",
+                    ItemStickyMenu { to: "/haar", "Haar Cascade" }
+                    ItemStickyMenu { to: "/", "Diff & Connect" }
+                    ItemStickyMenu { to: "/background", "Background" }
+                    ItemStickyMenu { to: "/batch", "Batch" }
+                    ItemStickyMenu { to: "/video", "Video" }
+                    ItemStickyMenu { to: "/history", "History" }
+                    ItemStickyMenu { to: "/doctor", "Doctor" }
+                    div {
+                        "onclick": "{SCRIPT}",
+                        class: "cursor-pointer hover:text-gray-200",
+                        div {
+                            class: "hidden dark:block",
+                            MoonIcon {}
+                        }
+                        div {
+                            class: "dark:hidden block",
+                            SunIcon {}
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn Footer(cx: Scope) -> Element {
+    cx.render(rsx! {
+        footer {
+            class:"h-10 bg-titlebar bg-titlebar p-2 backdrop-filter backdrop-blur-xl",
+            div {
+                class: "flex items-center justify-center text-sm space-x-10 ",
+                div {
+                    p {
+                        class: "text-white font-sans font-thin",
+                        "Osornio & Toledo @ Universidad Panamericana"
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Error shown by the toast of [`Main`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub summary: String,
+    /// What caused it, one cause per line, hidden until the user expands it.
+    pub details: String,
+}
+
+impl Failure {
+    pub fn new(summary: impl Into<String>) -> Self {
+        Failure { summary: summary.into(), details: "".to_owned() }
+    }
+
+    /// Uses the message of the error as summary and its sources as details.
+    pub fn from_error(err: &(dyn std::error::Error + 'static)) -> Self {
+        let details = std::iter::successors(err.source(), |err| err.source())
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Failure { summary: err.to_string(), details }
+    }
+}
+
+#[inline_props]
+fn Toast<'a>(cx: Scope, failure: &'a UseState<Option<Failure>>) -> Element {
+    let expanded: &UseState<bool> = use_state(&cx, || false);
+    let current = match failure.get() {
+        Some(current) => current,
+        None => return None,
+    };
+    let (summary, details) = (&current.summary, &current.details);
+    let toggle = if *expanded.get() { "Hide details" } else { "Show details" };
+
+    cx.render(rsx! {
+        div {
+            style: "z-index: 20;",
+            class: "fixed bottom-4 right-4 w-1/3 bg-red-500 text-white rounded-md p-3 shadow-lg",
+            div {
+                class: "flex items-start justify-between",
+                p { class: "text-sm", "{summary}" }
+                button {
+                    class: "ml-2 hover:text-gray-200",
+                    onclick: move |_| {
+                        expanded.set(false);
+                        failure.set(None);
+                    },
+                    "✕"
+                }
+            }
+            (!details.is_empty()).then(|| rsx! {
+                button {
+                    class: "text-xs underline mt-1 hover:text-gray-200",
+                    onclick: move |_| expanded.set(!expanded.get()),
+                    "{toggle}"
+                }
+                expanded.then(|| rsx! {
+                    pre {
+                        class: "text-xs whitespace-pre-wrap mt-1 max-h-48 overflow-auto select-text",
+                        "{details}"
+                    }
+                })
+            })
+        }
+    })
+}
+
+/// Layout of every page, `failure` is shown as a toast until the user closes it.
+#[inline_props]
+pub fn Main<'a>(
+    cx: Scope,
+    footer: bool,
+    failure: &'a UseState<Option<Failure>>,
+    children: Element<'a>,
+) -> Element {
+    cx.render(rsx! {
+        script { dangerous_inner_html: r#"document.body.classList.add("bg-neutral-100", "dark:bg-neutral-900");"# },
+        div {
+            class:"bg-neutral-100 dark:bg-neutral-900 text-dark dark:text-white select-none",
+            div {
+                Sticky {}
+                div { class:"h-screen mt-10 bg-neutral-100 dark:bg-neutral-900 text-dark dark:text-white",
+                    children
+                }
+                footer.then(|| rsx!{ Footer {} })
+            }
+            Toast { failure: failure }
+        }
+    })
+}
+
+/// Opens the desktop window.
+pub fn launch() {
+    dioxus_desktop::launch_cfg(
+        app,
+        Config::new().with_custom_head(format!(
+            r##"<style>{}</style>
+            <meta id="t_color" name="theme-color"/>
+            <script>
+            const html = document.getElementsByTagName('html')[0];
+            if (localStorage.theme === 'dark' || (!('theme' in localStorage) && window.matchMedia('(prefers-color-scheme: dark)').matches)) {{
+                document.getElementById("t_color").content = "rgb(17 24 39 / 0.9)"
+                html.classList.add('dark');
+                localStorage.theme = 'dark'
+            }} else {{
+                document.getElementById("t_color").content = "rgb(243 244 246 / var(--tw-bg-opacity))"
+                html.classList.remove('dark');
+                localStorage.theme = 'light'
+            }}
+            </script>
+            "##,
+            include_str!("../../public/assets/tailwind.css")
+        )),
+    );
+}
+
+
+/// Home directory of the user, where the file dialogs open.
+fn home_dir() -> PathBuf {
+    directories::UserDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Extensions offered by the file dialogs that pick images.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// Text field with a "Browse" button, `valid` holds the path typed or picked while it is a file.
+///
+/// The dialog only shows files with one of the `extensions`, described as `filter`.
+#[inline_props]
+fn PathPicker<'a>(
+    cx: Scope,
+    placeholder: &'a UseState<String>,
+    valid: &'a UseState<Option<PathBuf>>,
+    filter: &'a str,
+    extensions: &'a [&'a str],
+) -> Element {
+    // A typed path that isn't a file is flagged, instead of running on the last valid one
+    let missing = !placeholder.trim().is_empty() && valid.is_none();
+
+    cx.render(rsx! {
+        div{
+            class: "flex items-center justify-center mt-2",
+            input {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-4/5",
+                "type": "text",
+                value: "{placeholder}",
+                oninput: move |evt| {
+                    placeholder.set(evt.value.to_owned());
+                    let path = PathBuf::from(evt.value.trim());
+                    valid.set(path.is_file().then_some(path));
+                },
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/5",
+                "type": "button",
+                onclick: |_| {
+                    let path = rfd::FileDialog::new()
+                    .add_filter(filter, extensions)
+                    .set_directory(home_dir())
+                    .pick_file();
+
+                    if let Some(path) = path {
+                        placeholder.set(path.display().to_string());
+                        valid.set(Some(path));
+                    }
+                },
+                "Browse"
+            }
+        }
+        missing.then(|| rsx! {
+            p {
+                class: "text-sm text-red-500 mt-1",
+                "There is no file at this path"
+            }
+        })
+    })
+}
+
+/// Page that runs any [`Detector`] over the images picked by the user.
+///
+/// Method specific controls can be passed as children, they are shown above the "Do it!" button.
+#[inline_props]
+fn MethodPage<'a>(cx: Scope, detector: Arc<dyn Detector>, children: Element<'a>) -> Element {
+    let base64_image: &UseState<String> = use_state(&cx, || "".to_owned());
+    let base64_image_ready: &UseState<bool> = use_state(&cx, || false);
+    let cars_in_image: &UseState::<usize> = use_state(&cx, || 0);
+    let outcome: &UseState<Option<Arc<Outcome>>> = use_state(&cx, || None);
+    // Set while a task runs, storing `true` in the flag skips the stages left
+    let task: &UseState<Option<(TaskId, Arc<AtomicBool>)>> = use_state(&cx, || None);
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+    let regions: &UseState<Vec<Region>> = use_state(&cx, Vec::new);
+    let region_counts: &UseState<Vec<RegionCount>> = use_state(&cx, Vec::new);
+    // First image, shown to draw the regions on
+    let frame: &UseState<Option<Frame>> = use_state(&cx, || None);
+
+    let placeholder_path_1: &UseState<String> = use_state(&cx, || home_dir().display().to_string());
+    let valid_path_1: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+
+    let placeholder_path_2: &UseState<String> = use_state(&cx, || home_dir().display().to_string());
+    let valid_path_2: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+
+    let name = detector.name();
+    let kind = detector.kind();
+
+    let action = if let Some((id, cancelled)) = task.get() {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    // The detection itself can't be interrupted, it ends without being saved
+                    cancelled.store(true, Ordering::Relaxed);
+                    cx.remove_future(*id);
+                    task.set(None);
+                },
+                Spinner {}
+                "Cancel"
+            }
+        }
+    } else {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    let picked = [valid_path_1.get().as_deref(), valid_path_2.get().as_deref()];
+                    let (paths, ext) = match check_inputs(&picked[..kind.inputs()]) {
+                        Ok(inputs) => inputs,
+                        Err(err) => {
+                            base64_image_ready.set(false);
+                            failure.set(Some(err));
+                            return;
+                        }
+                    };
+
+                    let run_regions = regions.get().clone();
+                    let detector = detector.restricted_to(run_regions.clone());
+                    let (base64_image, base64_image_ready) = (base64_image.clone(), base64_image_ready.clone());
+                    let (cars_in_image, outcome_handle) = (cars_in_image.clone(), outcome.clone());
+                    let (task_handle, failure_handle, region_counts_handle) = (task.clone(), failure.clone(), region_counts.clone());
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    let flag = cancelled.clone();
+                    failure.set(None);
+
+                    let id = cx.spawn(async move {
+                        // Python holds the GIL during the whole detection, keep it away from the UI thread
+                        let result = tokio::task::spawn_blocking(move || {
+                            let inputs: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+                            let input = Input::new(kind, &inputs)
+                                .ok_or_else(|| anyhow::anyhow!("{} expects {} images", detector.name(), kind.inputs()))?;
+                            let outcome = detector.detect(input, &ext)?;
+                            check_cancelled(&flag)?;
+                            history::save(detector.as_ref(), &inputs, &ext, &outcome)?;
+                            let counts = roi::counts(&run_regions, &outcome.detections);
+                            let image = data_url(&ext, &outcome.image);
+                            Ok::<_, DetectError>((outcome, counts, image))
+                        }).await;
+
+                        match result {
+                            Ok(Ok((outcome, counts, image))) => {
+                                base64_image.set(image);
+                                cars_in_image.set(outcome.count());
+                                region_counts_handle.set(counts);
+                                outcome_handle.set(Some(Arc::new(outcome)));
+                                base64_image_ready.set(true);
+                            }
+                            Ok(Err(err)) => {
+                                base64_image_ready.set(false);
+                                failure_handle.set(Some(Failure::from_error(&err)));
+                            }
+                            Err(err) => {
+                                base64_image_ready.set(false);
+                                failure_handle.set(Some(Failure {
+                                    summary: "The detection stopped unexpectedly".to_owned(),
+                                    details: err.to_string(),
+                                }));
+                            }
+                        }
+                        task_handle.set(None);
+                    });
+                    task.set(Some((id, cancelled)));
+                },
+                "Do it!"
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "{name} Method"
+                }
+                div {
+                    class: "w-4/5",
+                    PathPicker { placeholder: placeholder_path_1, valid: valid_path_1, filter: "image", extensions: IMAGE_EXTENSIONS }
+                    (kind == Kind::Pair).then(|| rsx! {
+                        PathPicker { placeholder: placeholder_path_2, valid: valid_path_2, filter: "image", extensions: IMAGE_EXTENSIONS }
+                    })
+                    children
+                    button {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                        onclick: move |_| match valid_path_1.get() {
+                            Some(path) if path.is_file() => {
+                                match std::fs::read(path).map_err(anyhow::Error::from).and_then(|contents| frame_of(&contents)) {
+                                    Ok(loaded) => frame.set(Some(loaded)),
+                                    Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+                                }
+                            }
+                            _ => failure.set(Some(Failure::new("Pick an existing image for input 1"))),
+                        },
+                        "Draw regions of interest on the image"
+                    }
+                    frame.get().as_ref().map(|frame| rsx! {
+                        RegionEditor { frame: frame, regions: regions, failure: failure }
+                    })
+                    div {
+                        class: "flex justify-center items-center",
+                        action
+                    }
+                    div {
+                        class: "flex justify-center items-center mt-5",
+                        div {
+                            class: "flex flex-col items-center",
+                            base64_image_ready.then(|| rsx! {
+                                p {
+                                    class: "text-center",
+                                    "There are {cars_in_image} cars in the image!"
+                                }
+                                region_counts.iter().map(|region| {
+                                    let (region_name, count) = (&region.name, region.count);
+                                    rsx! {
+                                        p { class: "text-center text-sm", "{region_name}: {count} cars" }
+                                    }
+                                })
+                                img {
+                                    class: "mt-2 w-2/3",
+                                    src: "{base64_image}"
+                                }
+                                outcome.get().as_ref().map(|outcome| rsx! {
+                                    SaveResult { outcome: outcome.as_ref(), failure: failure }
+                                })
+                            })
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Saves the annotated image of a detection where the user picks, in the format they choose.
+#[inline_props]
+fn SaveResult<'a>(cx: Scope, outcome: &'a Outcome, failure: &'a UseState<Option<Failure>>) -> Element {
+    let options: &UseState<export::Options> = use_state(&cx, export::Options::default);
+    let saved: &UseState<String> = use_state(&cx, || "".to_owned());
+
+    let current = *options.get();
+    let (format, quality, sidecar) = (current.format.id(), current.quality as f64, current.sidecar);
+
+    cx.render(rsx! {
+        div {
+            class: "w-2/3 mt-5 mb-5",
+            label {
+                class: "flex flex-col text-sm",
+                "Format"
+                select {
+                    class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                    value: "{format}",
+                    onchange: move |evt| {
+                        if let Some(format) = export::Format::from_id(&evt.value) {
+                            options.modify(|options| export::Options { format, ..*options });
+                        }
+                    },
+                    export::Format::ALL.iter().map(|f| {
+                        let (id, name) = (f.id(), f.name());
+                        rsx! { option { key: "{id}", value: "{id}", "{name}" } }
+                    })
+                }
+            }
+            (current.format == export::Format::Jpeg).then(|| rsx! {
+                div {
+                    class: "mt-2",
+                    ParamInput {
+                        label: "Quality",
+                        value: quality,
+                        step: 1.0,
+                        onchange: move |value: f64| options.modify(|options| export::Options {
+                            quality: value.clamp(1., 100.) as u8,
+                            ..*options
+                        }),
+                    }
+                }
+            })
+            label {
+                class: "flex items-center text-sm mt-2",
+                input {
+                    class: "mr-2",
+                    "type": "checkbox",
+                    checked: "{sidecar}",
+                    oninput: move |evt| options.modify(|options| export::Options {
+                        sidecar: evt.value == "true",
+                        ..*options
+                    }),
+                }
+                "Also save the detections as JSON"
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    let format = current.format;
+                    let path = rfd::FileDialog::new()
+                    .add_filter(format.name(), &[format.id()])
+                    .set_directory(home_dir())
+                    .set_file_name(&format!("result.{}", format.id()))
+                    .save_file();
+
+                    if let Some(path) = path {
+                        match export::save(outcome, &path, &current) {
+                            Ok(_) => saved.set(format!("Saved to {}", path.display())),
+                            Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+                        }
+                    }
+                },
+                "Save result"
+            }
+            p {
+                class: "text-sm mt-1 select-text",
+                "{saved}"
+            }
+        }
+    })
+}
+
+/// Checks that every picked image exists.
+///
+/// Returns the images and the extension of the first one, the annotated image is encoded like it.
+fn check_inputs(picked: &[Option<&Path>]) -> Result<(Vec<PathBuf>, String), Failure> {
+    let mut paths = vec![];
+    for (i, path) in picked.iter().enumerate() {
+        match path {
+            Some(path) if path.is_file() => paths.push(path.to_path_buf()),
+            _ => return Err(Failure::new(format!("Pick an existing image for input {}", i + 1))),
+        }
+    }
+
+    let first = &paths[0];
+    let ext = first.extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| Failure::new(format!("{} has no extension", first.display())))?
+        .to_owned();
+    Ok((paths, ext))
+}
+
+/// Fails once the user cancelled the task, skipping the stages left.
+///
+/// The task was already dropped by then, so the error is never shown.
+fn check_cancelled(cancelled: &AtomicBool) -> Result<(), DetectError> {
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(anyhow::anyhow!("Cancelled").into());
+    }
+    Ok(())
+}
+
+/// Small spinning circle shown while a detection runs.
+fn Spinner(cx: Scope) -> Element {
+    cx.render(rsx! {
+        div {
+            class: "inline-block align-middle mr-2 h-4 w-4 animate-spin rounded-full border-2 border-current border-t-transparent"
+        }
+    })
+}
+
+fn DiffMethod(cx: Scope) -> Element {
+    let params: &UseState<DiffParams> = use_state(&cx, DiffParams::default);
+    let backend: &UseState<Backend> = use_state(&cx, Backend::default);
+
+    cx.render(rsx! {
+        MethodPage {
+            detector: Arc::new(DiffConnect::new(*params.get()).with_backend(*backend.get())),
+            BackendSelect { backend: backend }
+            DiffControls { params: params }
+        }
+    })
+}
+
+#[inline_props]
+fn DiffControls<'a>(cx: Scope, params: &'a UseState<DiffParams>) -> Element {
+    let current = *params.get();
+    let preset = DiffParams::PRESETS.iter().copied().find(|name| DiffParams::preset(name) == Some(current));
+    let error = current.validate().err().map(|err| err.to_string()).unwrap_or_default();
+
+    cx.render(rsx! {
+        PresetSelect {
+            presets: DiffParams::PRESETS,
+            current: preset,
+            onchange: move |name: String| {
+                if let Some(preset) = DiffParams::preset(&name) {
+                    params.set(preset);
+                }
+            },
+        }
+        div {
+            class: "grid grid-cols-4 gap-2 mt-2",
+            ParamInput {
+                label: "Working size", value: current.work_size as f64, step: 10.,
+                onchange: move |v: f64| params.set(DiffParams { work_size: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Blur kernel", value: current.blur as f64, step: 2.,
+                onchange: move |v: f64| params.set(DiffParams { blur: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Threshold", value: current.threshold as f64, step: 1.,
+                onchange: move |v: f64| params.set(DiffParams { threshold: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Kernel rows", value: current.kernel.0 as f64, step: 1.,
+                onchange: move |v: f64| params.set(DiffParams { kernel: (v as i32, current.kernel.1), ..current }),
+            }
+            ParamInput {
+                label: "Kernel columns", value: current.kernel.1 as f64, step: 1.,
+                onchange: move |v: f64| params.set(DiffParams { kernel: (current.kernel.0, v as i32), ..current }),
+            }
+            ParamInput {
+                label: "Dilations", value: current.dilate_iterations as f64, step: 1.,
+                onchange: move |v: f64| params.set(DiffParams { dilate_iterations: v as i32, ..current }),
+            }
+        }
+        (!error.is_empty()).then(|| rsx! {
+            p {
+                class: "text-sm text-red-500 mt-2",
+                "{error}"
+            }
+        })
+    })
+}
+
+fn HaarMethod(cx: Scope) -> Element {
+    let params: &UseState<HaarParams> = use_state(&cx, HaarParams::default);
+    let library: &UseRef<Library> = use_ref(&cx, || Library::load().unwrap_or_default());
+    let backend: &UseState<Backend> = use_state(&cx, Backend::default);
+
+    let detector = haar_detector(*params.get(), *backend.get(), &library.read());
+
+    cx.render(rsx! {
+        MethodPage {
+            detector: Arc::new(detector),
+            BackendSelect { backend: backend }
+            CascadePicker { library: library }
+            HaarControls { params: params }
+        }
+    })
+}
+
+/// Haar Cascade with the cascade last picked from the library.
+fn haar_detector(params: HaarParams, backend: Backend, library: &Library) -> HaarCascade {
+    let detector = HaarCascade::new(params).with_backend(backend);
+    match library.last_used().and_then(|cascade| library.path(cascade).ok()) {
+        Some(xml) => detector.with_cascade(xml),
+        None => detector,
+    }
+}
+
+/// Method of the [`detect::registry`] with the given id, set up with the values of its controls.
+fn configured(method: &str, backend: Backend, haar: HaarParams, diff: DiffParams, library: &Library) -> Option<Arc<dyn Detector>> {
+    match method {
+        "haar" => Some(Arc::new(haar_detector(haar, backend, library))),
+        "diff" => Some(Arc::new(DiffConnect::new(diff).with_backend(backend))),
+        _ => None,
+    }
+}
+
+/// Controls of the method with the given id, for the pages where any method can be picked.
+#[inline_props]
+fn MethodControls<'a>(
+    cx: Scope,
+    method: &'a str,
+    haar: &'a UseState<HaarParams>,
+    diff: &'a UseState<DiffParams>,
+    library: &'a UseRef<Library>,
+) -> Element {
+    match *method {
+        "haar" => cx.render(rsx! {
+            CascadePicker { library: library }
+            HaarControls { params: haar }
+        }),
+        "diff" => cx.render(rsx! {
+            DiffControls { params: diff }
+        }),
+        _ => None,
+    }
+}
+
+/// Picks the cascade used by the Haar Cascade method and installs new ones into the library.
+#[inline_props]
+fn CascadePicker<'a>(cx: Scope, library: &'a UseRef<Library>) -> Element {
+    let name: &UseState<String> = use_state(&cx, || "".to_owned());
+    let description: &UseState<String> = use_state(&cx, || "".to_owned());
+    let error: &UseState<String> = use_state(&cx, || "".to_owned());
+
+    let (cascades, current) = {
+        let library = library.read();
+        (library.cascades.clone(), library.last_used().cloned())
+    };
+    let (current_name, current_description) = current
+        .map(|cascade| (cascade.name, cascade.description))
+        .unwrap_or_default();
+
+    cx.render(rsx! {
+        label {
+            class: "flex flex-col text-sm mt-2",
+            "Cascade"
+            select {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                value: "{current_name}",
+                onchange: move |evt| {
+                    match library.write().set_last_used(&evt.value) {
+                        Ok(()) => error.set("".to_owned()),
+                        Err(err) => error.set(err.to_string()),
+                    }
+                },
+                cascades.iter().map(|cascade| {
+                    let name = &cascade.name;
+                    rsx! { option { key: "{name}", value: "{name}", "{name}" } }
+                })
+            }
+        }
+        p {
+            class: "text-sm text-neutral-500 mt-1",
+            "{current_description}"
+        }
+        div {
+            class: "flex items-center justify-center mt-2",
+            input {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-1/5",
+                "type": "text",
+                placeholder: "Name",
+                value: "{name}",
+                oninput: move |evt| name.set(evt.value.clone()),
+            }
+            input {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-3/5",
+                "type": "text",
+                placeholder: "Description",
+                value: "{description}",
+                oninput: move |evt| description.set(evt.value.clone()),
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/5",
+                "type": "button",
+                onclick: move |_| {
+                    let path = rfd::FileDialog::new()
+                    .add_filter("cascade", &["xml"])
+                    .set_directory(home_dir())
+                    .pick_file();
+
+                    if let Some(path) = path {
+                        let mut library = library.write();
+                        let result = library.install(&path, name.get(), description.get())
+                            .and_then(|_| library.set_last_used(name.get().trim()));
+                        match result {
+                            Ok(()) => {
+                                error.set("".to_owned());
+                                name.set("".to_owned());
+                                description.set("".to_owned());
+                            }
+                            Err(err) => error.set(err.to_string()),
+                        }
+                    }
+                },
+                "Install"
+            }
+        }
+        (!error.is_empty()).then(|| rsx! {
+            p {
+                class: "text-sm text-red-500 mt-2",
+                "{error}"
+            }
+        })
+    })
+}
+
+/// Number field for a method parameter, only calls `onchange` with values that parse.
+#[inline_props]
+fn ParamInput<'a>(cx: Scope, label: &'a str, value: f64, step: f64, onchange: EventHandler<'a, f64>) -> Element {
+    cx.render(rsx! {
+        label {
+            class: "flex flex-col text-sm",
+            "{label}"
+            input {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                "type": "number",
+                step: "{step}",
+                value: "{value}",
+                oninput: move |evt| {
+                    if let Ok(value) = evt.value.parse() {
+                        onchange.call(value);
+                    }
+                },
+            }
+        }
+    })
+}
+
+/// Select with the backends of this build, hidden when there's only one.
+#[inline_props]
+fn BackendSelect<'a>(cx: Scope, backend: &'a UseState<Backend>) -> Element {
+    if Backend::ALL.len() < 2 {
+        return None;
+    }
+    let current = backend.get().id();
+
+    cx.render(rsx! {
+        label {
+            class: "flex flex-col text-sm mt-2",
+            "Backend"
+            select {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                value: "{current}",
+                onchange: move |evt| {
+                    if let Some(selected) = Backend::from_id(&evt.value) {
+                        backend.set(selected);
+                    }
+                },
+                Backend::ALL.iter().map(|b| {
+                    let (id, name) = (b.id(), b.name());
+                    rsx! { option { key: "{id}", value: "{id}", "{name}" } }
+                })
+            }
+        }
+    })
+}
+
+/// Select with the presets of a method, shows "Custom" once a value is changed by hand.
+#[inline_props]
+fn PresetSelect<'a>(cx: Scope, presets: &'static [&'static str], current: Option<&'static str>, onchange: EventHandler<'a, String>) -> Element {
+    let current = current.unwrap_or("custom");
+
+    cx.render(rsx! {
+        label {
+            class: "flex flex-col text-sm mt-2",
+            "Preset"
+            select {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                value: "{current}",
+                onchange: move |evt| onchange.call(evt.value.clone()),
+                presets.iter().map(|name| rsx! {
+                    option { key: "{name}", value: "{name}", "{name}" }
+                })
+                option { value: "custom", disabled: "true", "custom" }
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn HaarControls<'a>(cx: Scope, params: &'a UseState<HaarParams>) -> Element {
+    let current = *params.get();
+    let preset = HaarParams::PRESETS.iter().copied().find(|name| HaarParams::preset(name) == Some(current));
+    let error = current.validate().err().map(|err| err.to_string()).unwrap_or_default();
+
+    cx.render(rsx! {
+        PresetSelect {
+            presets: HaarParams::PRESETS,
+            current: preset,
+            onchange: move |name: String| {
+                if let Some(preset) = HaarParams::preset(&name) {
+                    params.set(preset);
+                }
+            },
+        }
+        div {
+            class: "grid grid-cols-4 gap-2 mt-2",
+            ParamInput {
+                label: "Scale factor", value: current.scale_factor, step: 0.01,
+                onchange: move |v| params.set(HaarParams { scale_factor: v, ..current }),
+            }
+            ParamInput {
+                label: "Min neighbours", value: current.min_neighbors as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { min_neighbors: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Min width", value: current.min_size.0 as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { min_size: (v as i32, current.min_size.1), ..current }),
+            }
+            ParamInput {
+                label: "Min height", value: current.min_size.1 as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { min_size: (current.min_size.0, v as i32), ..current }),
+            }
+            ParamInput {
+                label: "Max width", value: current.max_size.0 as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { max_size: (v as i32, current.max_size.1), ..current }),
+            }
+            ParamInput {
+                label: "Max height", value: current.max_size.1 as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { max_size: (current.max_size.0, v as i32), ..current }),
+            }
+            ParamInput {
+                label: "Blur kernel", value: current.blur as f64, step: 2.,
+                onchange: move |v: f64| params.set(HaarParams { blur: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Dilate kernel", value: current.dilate as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { dilate: v as i32, ..current }),
+            }
+            ParamInput {
+                label: "Close kernel", value: current.close as f64, step: 1.,
+                onchange: move |v: f64| params.set(HaarParams { close: v as i32, ..current }),
+            }
+        }
+        (!error.is_empty()).then(|| rsx! {
+            p {
+                class: "text-sm text-red-500 mt-2",
+                "{error}"
+            }
+        })
+    })
+}
+
+/// Runs a method over every image of a folder and shows the count of each one.
+fn BatchPage(cx: Scope) -> Element {
+    let source: &UseState<PathBuf> = use_state(&cx, home_dir);
+    let method: &UseState<String> = use_state(&cx, || "haar".to_owned());
+    let backend: &UseState<Backend> = use_state(&cx, Backend::default);
+    let haar_params: &UseState<HaarParams> = use_state(&cx, HaarParams::default);
+    let diff_params: &UseState<DiffParams> = use_state(&cx, DiffParams::default);
+    let library: &UseRef<Library> = use_ref(&cx, || Library::load().unwrap_or_default());
+    let rows: &UseRef<Vec<batch::Row>> = use_ref(&cx, Vec::new);
+    // Folder of the annotated images of the last batch
+    let saved_in: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+    let progress: &UseState<(usize, usize)> = use_state(&cx, || (0, 0));
+    // Set while a batch runs, storing `true` stops it after the current image
+    let stop: &UseState<Option<Arc<AtomicBool>>> = use_state(&cx, || None);
+    let tracking: &UseState<bool> = use_state(&cx, || false);
+    let track_params: &UseState<TrackerParams> = use_state(&cx, TrackerParams::default);
+    let regions: &UseState<Vec<Region>> = use_state(&cx, Vec::new);
+    // Regions of the last batch, the table has a column for each include region
+    let batch_regions: &UseState<Vec<Region>> = use_state(&cx, Vec::new);
+    // First image of the folder, shown to draw the regions on
+    let frame: &UseState<Option<Frame>> = use_state(&cx, || None);
+
+    let region_names: Vec<String> = roi::includes(batch_regions.get()).map(|region| region.name.clone()).collect();
+    let table: Vec<(String, String, u128, Vec<usize>)> = rows.read().iter().map(|row| {
+        let (count, region_counts) = match &row.outcome {
+            Ok(outcome) => {
+                let counts = roi::counts(batch_regions.get(), &outcome.detections).into_iter().map(|region| region.count).collect();
+                (outcome.count().to_string(), counts)
+            }
+            Err(err) => (format!("Error: {err}"), vec![]),
+        };
+        (row.name(), count, row.elapsed.as_millis(), region_counts)
+    }).collect();
+    let elapsed: u128 = table.iter().map(|(_, _, millis, _)| millis).sum();
+    let source_text = source.display().to_string();
+    let total: usize = rows.read().iter()
+        .filter_map(|row| row.outcome.as_ref().ok())
+        .map(|outcome| outcome.count())
+        .sum();
+    let region_totals: Vec<usize> = (0..region_names.len())
+        .map(|i| table.iter().filter_map(|(_, _, _, counts)| counts.get(i)).sum())
+        .collect();
+    // The images are taken as consecutive frames, those that failed as frames without cars
+    let unique = tracking.then(|| {
+        let frames: Vec<_> = rows.read().iter()
+            .map(|row| row.outcome.as_ref().map(|outcome| outcome.detections.clone()).unwrap_or_default())
+            .collect();
+        track::track(&frames, track_params.get())
+            .map(|tracks| tracks.unique().to_string())
+            .unwrap_or_else(|err| err.to_string())
+    });
+
+    let action = if let Some(flag) = stop.get() {
+        let (done, total) = *progress.get();
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| flag.store(true, Ordering::Relaxed),
+                Spinner {}
+                "Cancel ({done}/{total})"
+            }
+        }
+    } else {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    let detector = configured(method.get(), *backend.get(), *haar_params.get(), *diff_params.get(), &library.read());
+                    let detector = match detector {
+                        Some(detector) => detector.restricted_to(regions.get().clone()),
+                        None => return,
+                    };
+                    let images = batch::collect(source.get())
+                        .and_then(|images| Ok((images, batch::output_dir()?)));
+
+                    let (images, save_in) = match images {
+                        Ok(images) => images,
+                        Err(err) => {
+                            rows.set(vec![]);
+                            saved_in.set(None);
+                            failure.set(Some(Failure::from_error(err.as_ref())));
+                            return;
+                        }
+                    };
+                    saved_in.set(Some(save_in.clone()));
+                    batch_regions.set(regions.get().clone());
+
+                    let outputs = batch::outputs(&images, &save_in);
+                    let jobs = batch::jobs(detector.kind(), &images);
+                    let flag = Arc::new(AtomicBool::new(false));
+                    failure.set(None);
+                    rows.set(vec![]);
+                    progress.set((0, jobs.len()));
+                    stop.set(Some(flag.clone()));
+
+                    let (rows, progress, stop) = (rows.clone(), progress.clone(), stop.clone());
+                    cx.spawn(async move {
+                        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+                        tokio::task::spawn_blocking(move || {
+                            for (inputs, output) in jobs.into_iter().zip(&outputs) {
+                                if flag.load(Ordering::Relaxed) {
+                                    break;
+                                }
+                                if tx.send(batch::run(detector.as_ref(), inputs, output)).is_err() {
+                                    break;
+                                }
+                            }
+                        });
+
+                        while let Some(row) = rx.recv().await {
+                            rows.write().push(row);
+                            progress.modify(|(done, total)| (done + 1, *total));
+                        }
+                        stop.set(None);
+                    });
+                },
+                "Do it!"
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "Batch"
+                }
+                div {
+                    class: "w-4/5",
+                    div{
+                        class: "flex items-center justify-center",
+                        input {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-4/5",
+                            "type": "text",
+                            value: "{source_text}",
+                            oninput: move |evt| source.set(PathBuf::from(evt.value.trim())),
+                        }
+                        button {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/5",
+                            "type": "button",
+                            onclick: |_| {
+                                let path = rfd::FileDialog::new()
+                                .set_directory(home_dir())
+                                .pick_folder();
+
+                                if let Some(path) = path {
+                                    source.set(path);
+                                }
+                            },
+                            "Browse"
+                        }
+                    }
+                    select {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                        value: "{method}",
+                        onchange: move |evt| method.set(evt.value.clone()),
+                        detect::registry(*backend.get()).into_iter().map(|detector| {
+                            let (id, name) = (detector.id(), detector.name());
+                            rsx! { option { key: "{id}", value: "{id}", "{name}" } }
+                        })
+                    }
+                    BackendSelect { backend: backend }
+                    MethodControls { method: method.get(), haar: haar_params, diff: diff_params, library: library }
+                    TrackControls { enabled: tracking, params: track_params }
+                    button {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                        onclick: move |_| {
+                            let loaded = batch::collect(source.get())
+                                .and_then(|images| Ok(std::fs::read(&images[0])?))
+                                .and_then(|contents| frame_of(&contents));
+                            match loaded {
+                                Ok(loaded) => frame.set(Some(loaded)),
+                                Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+                            }
+                        },
+                        "Draw regions of interest on the first image"
+                    }
+                    frame.get().as_ref().map(|frame| rsx! {
+                        RegionEditor { frame: frame, regions: regions, failure: failure }
+                    })
+                    div {
+                        class: "flex justify-center items-center",
+                        action
+                    }
+                    saved_in.get().as_ref().map(|dir| {
+                        let dir = dir.display().to_string();
+                        rsx! {
+                            p {
+                                class: "text-sm mt-2 select-text",
+                                "The annotated images are in {dir}"
+                            }
+                        }
+                    })
+                    (!table.is_empty()).then(|| rsx! {
+                        table {
+                            class: "table-auto w-full mt-5 mb-5",
+                            thead {
+                                tr {
+                                    th { class: "text-left p-2", "File" }
+                                    th { class: "text-right p-2", "Time" }
+                                    th { class: "text-right p-2", "Cars" }
+                                    region_names.iter().map(|region_name| rsx! {
+                                        th { class: "text-right p-2", "{region_name}" }
+                                    })
+                                }
+                            }
+                            tbody {
+                                // Images of different folders may have the same name
+                                table.iter().enumerate().map(|(i, (name, count, millis, region_counts))| rsx! {
+                                    tr {
+                                        key: "{i}",
+                                        class: "border-t border-neutral-300 dark:border-neutral-700",
+                                        td { class: "p-2", "{name}" }
+                                        td { class: "text-right p-2", "{millis} ms" }
+                                        td { class: "text-right p-2", "{count}" }
+                                        region_counts.iter().map(|region_count| rsx! {
+                                            td { class: "text-right p-2", "{region_count}" }
+                                        })
+                                    }
+                                })
+                                tr {
+                                    class: "border-t border-neutral-300 dark:border-neutral-700 font-bold",
+                                    td { class: "p-2", "Total" }
+                                    td { class: "text-right p-2", "{elapsed} ms" }
+                                    td { class: "text-right p-2", "{total}" }
+                                    region_totals.iter().map(|region_total| rsx! {
+                                        td { class: "text-right p-2", "{region_total}" }
+                                    })
+                                }
+                                unique.as_ref().map(|unique| rsx! {
+                                    tr {
+                                        class: "border-t border-neutral-300 dark:border-neutral-700 font-bold",
+                                        td { class: "p-2", "Different cars" }
+                                        td { class: "p-2" }
+                                        td { class: "text-right p-2", "{unique}" }
+                                    }
+                                })
+                            }
+                        }
+                    })
+                }
+            }
+        }
+    })
+}
+
+/// Estimates the empty scene from a folder of frames and counts the cars of an image against it.
+fn BackgroundPage(cx: Scope) -> Element {
+    let source: &UseState<PathBuf> = use_state(&cx, home_dir);
+    let background_params: &UseState<BackgroundParams> = use_state(&cx, BackgroundParams::default);
+    let params: &UseState<DiffParams> = use_state(&cx, DiffParams::default);
+    let backend: &UseState<Backend> = use_state(&cx, Backend::default);
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+    // Running estimation, cancelling it stores `true` in the flag
+    let task: &UseState<Option<(TaskId, Arc<AtomicBool>)>> = use_state(&cx, || None);
+    // Background and annotated image, both as data URLs, and the outcome of the detection
+    let result: &UseState<Option<(String, String, Arc<Outcome>)>> = use_state(&cx, || None);
+
+    let placeholder_image: &UseState<String> = use_state(&cx, || home_dir().display().to_string());
+    let valid_image: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+
+    let source_text = source.display().to_string();
+    let current = *background_params.get();
+    let estimator = current.estimator.id();
+
+    let action = if let Some((id, cancelled)) = task.get() {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    // The stage running ends, the ones after it and the history are skipped
+                    cancelled.store(true, Ordering::Relaxed);
+                    cx.remove_future(*id);
+                    task.set(None);
+                },
+                Spinner {}
+                "Cancel"
+            }
+        }
+    } else {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    let (image, ext) = match check_inputs(&[valid_image.get().as_deref()]) {
+                        Ok((mut paths, ext)) => (paths.remove(0), ext),
+                        Err(err) => {
+                            failure.set(Some(err));
+                            return;
+                        }
+                    };
+                    let frames = match batch::collect(source.get()) {
+                        Ok(frames) => frames,
+                        Err(err) => {
+                            failure.set(Some(Failure::from_error(err.as_ref())));
+                            return;
+                        }
+                    };
+
+                    let source = source.get().clone();
+                    let detector = DiffConnect::new(*params.get()).with_backend(*backend.get());
+                    let backend = *backend.get();
+                    let (result_handle, task_handle, failure_handle) = (result.clone(), task.clone(), failure.clone());
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    let flag = cancelled.clone();
+                    failure.set(None);
+
+                    let id = cx.spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            let frames: Vec<&Path> = frames.iter().map(PathBuf::as_path).collect();
+                            let output = background::default_output(&source, current.estimator)?;
+                            background::save(&frames, &current, backend, &output)?;
+                            check_cancelled(&flag)?;
+
+                            let outcome = detector.detect(Input::Pair(&image, &output), &ext)?;
+                            check_cancelled(&flag)?;
+                            history::save(&detector, &[image.as_path(), output.as_path()], &ext, &outcome)?;
+                            let background = std::fs::read(&output)
+                                .map_err(|err| DetectError::UnreadableImage(format!("{} ({err})", output.display())))?;
+                            let (background, image) = (data_url("png", &background), data_url(&ext, &outcome.image));
+                            Ok::<_, DetectError>((background, image, outcome))
+                        }).await;
+
+                        match result {
+                            Ok(Ok((background, image, outcome))) => {
+                                result_handle.set(Some((background, image, Arc::new(outcome))));
+                            }
+                            Ok(Err(err)) => {
+                                result_handle.set(None);
+                                failure_handle.set(Some(Failure::from_error(&err)));
+                            }
+                            Err(err) => {
+                                result_handle.set(None);
+                                failure_handle.set(Some(Failure {
+                                    summary: "The detection stopped unexpectedly".to_owned(),
+                                    details: err.to_string(),
+                                }));
+                            }
+                        }
+                        task_handle.set(None);
+                    });
+                    task.set(Some((id, cancelled)));
+                },
+                "Do it!"
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "Background"
+                }
+                div {
+                    class: "w-4/5",
+                    p {
+                        class: "text-sm",
+                        "Folder with frames of the scene, the cars that come and go are left out of the background"
+                    }
+                    div{
+                        class: "flex items-center justify-center mt-1",
+                        input {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-4/5",
+                            "type": "text",
+                            value: "{source_text}",
+                            oninput: move |evt| source.set(PathBuf::from(evt.value.trim())),
+                        }
+                        button {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/5",
+                            "type": "button",
+                            onclick: |_| {
+                                let path = rfd::FileDialog::new()
+                                .set_directory(home_dir())
+                                .pick_folder();
+
+                                if let Some(path) = path {
+                                    source.set(path);
+                                }
+                            },
+                            "Browse"
+                        }
+                    }
+                    p {
+                        class: "text-sm mt-2",
+                        "Image to count the cars of"
+                    }
+                    PathPicker { placeholder: placeholder_image, valid: valid_image, filter: "image", extensions: IMAGE_EXTENSIONS }
+                    label {
+                        class: "flex flex-col text-sm mt-2",
+                        "Estimator"
+                        select {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-1",
+                            value: "{estimator}",
+                            onchange: move |evt| {
+                                if let Some(estimator) = Estimator::from_id(&evt.value) {
+                                    background_params.set(BackgroundParams { estimator, ..current });
+                                }
+                            },
+                            Estimator::ALL.iter().map(|e| {
+                                let (id, name) = (e.id(), e.name());
+                                rsx! { option { key: "{id}", value: "{id}", "{name}" } }
+                            })
+                        }
+                    }
+                    (current.estimator == Estimator::Average).then(|| rsx! {
+                        div {
+                            class: "mt-2",
+                            ParamInput {
+                                label: "Rate", value: current.rate, step: 0.01,
+                                onchange: move |v| background_params.set(BackgroundParams { rate: v, ..current }),
+                            }
+                        }
+                    })
+                    BackendSelect { backend: backend }
+                    DiffControls { params: params }
+                    div {
+                        class: "flex justify-center items-center",
+                        action
+                    }
+                    result.get().as_ref().map(|(background, image, outcome)| {
+                        let count = outcome.count();
+                        rsx! {
+                            div {
+                                class: "flex flex-col items-center mt-5",
+                                p { class: "text-center", "There are {count} cars in the image!" }
+                                img {
+                                    class: "mt-2 w-2/3",
+                                    src: "{image}"
+                                }
+                                p { class: "text-sm mt-5", "Estimated background" }
+                                img {
+                                    class: "mt-2 w-2/3",
+                                    src: "{background}"
+                                }
+                                SaveResult { outcome: outcome.as_ref(), failure: failure }
+                            }
+                        }
+                    })
+                }
+            }
+        }
+    })
+}
+
+/// Runs a method over every frame of a video and plots the count of each one.
+fn VideoPage(cx: Scope) -> Element {
+    let method: &UseState<String> = use_state(&cx, || "haar".to_owned());
+    let backend: &UseState<Backend> = use_state(&cx, Backend::default);
+    let haar_params: &UseState<HaarParams> = use_state(&cx, HaarParams::default);
+    let diff_params: &UseState<DiffParams> = use_state(&cx, DiffParams::default);
+    let library: &UseRef<Library> = use_ref(&cx, || Library::load().unwrap_or_default());
+    let outcome: &UseState<Option<Arc<VideoOutcome>>> = use_state(&cx, || None);
+    let task: &UseState<Option<(TaskId, Arc<AtomicBool>)>> = use_state(&cx, || None);
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+    let saved: &UseState<String> = use_state(&cx, || "".to_owned());
+    let tracking: &UseState<bool> = use_state(&cx, || false);
+    let track_params: &UseState<TrackerParams> = use_state(&cx, TrackerParams::default);
+    // Ends of the counting line, in pixels of the frames, and the seconds of each time bucket
+    let line_points: &UseState<Vec<(f32, f32)>> = use_state(&cx, Vec::new);
+    let bucket: &UseState<f64> = use_state(&cx, || 60.);
+    // First frame, shown to draw the counting line and the regions on
+    let frame: &UseState<Option<Frame>> = use_state(&cx, || None);
+    let loading_frame: &UseState<bool> = use_state(&cx, || false);
+    // Regions being drawn, and the ones the counted video was restricted to
+    let regions: &UseState<Vec<Region>> = use_state(&cx, Vec::new);
+    let counted_regions: &UseState<Vec<Region>> = use_state(&cx, Vec::new);
+
+    let placeholder_video: &UseState<String> = use_state(&cx, || home_dir().display().to_string());
+    let valid_video: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+
+    let placeholder_reference: &UseState<String> = use_state(&cx, || "".to_owned());
+    let valid_reference: &UseState<Option<PathBuf>> = use_state(&cx, || None);
+
+    let detector = configured(method.get(), *backend.get(), *haar_params.get(), *diff_params.get(), &library.read());
+    let pair = detector.as_ref().map(|detector| detector.kind() == Kind::Pair).unwrap_or(false);
+
+    let action = if let Some((id, cancelled)) = task.get() {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    // The video keeps being read in the background, then its run is removed
+                    cancelled.store(true, Ordering::Relaxed);
+                    cx.remove_future(*id);
+                    task.set(None);
+                },
+                Spinner {}
+                "Cancel"
+            }
+        }
+    } else {
+        rsx! {
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                onclick: move |_| {
+                    let run_regions = regions.get().clone();
+                    let detector = match &detector {
+                        Some(detector) => detector.restricted_to(run_regions.clone()),
+                        None => return,
+                    };
+                    let video = match valid_video.get() {
+                        Some(video) if video.is_file() => video.clone(),
+                        _ => {
+                            failure.set(Some(Failure::new("Pick an existing video")));
+                            return;
+                        }
+                    };
+                    // An empty reference compares every frame with the first one
+                    let reference = match valid_reference.get() {
+                        _ if !pair || placeholder_reference.trim().is_empty() => None,
+                        Some(reference) => Some(reference.clone()),
+                        None => {
+                            failure.set(Some(Failure::new("Pick an existing image for the reference")));
+                            return;
+                        }
+                    };
+
+                    let (outcome_handle, task_handle, failure_handle) = (outcome.clone(), task.clone(), failure.clone());
+                    let counted_regions_handle = counted_regions.clone();
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    let flag = cancelled.clone();
+                    failure.set(None);
+                    saved.set("".to_owned());
+
+                    let id = cx.spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            let output = video::default_output(&video)?;
+                            let counted = detector.detect_video(&video, reference.as_deref(), &output)
+                                .and_then(|counted| check_cancelled(&flag).map(|_| counted));
+                            let counted = match counted {
+                                Ok(counted) => counted,
+                                Err(err) => {
+                                    // The folder of the run only has a partial video
+                                    let _ = history::discard_video(&output);
+                                    return Err(err);
+                                }
+                            };
+                            history::save_video(detector.as_ref(), &video, reference.as_deref(), &counted)?;
+                            Ok::<_, DetectError>(counted)
+                        }).await;
+
+                        match result {
+                            Ok(Ok(counted)) => {
+                                counted_regions_handle.set(run_regions);
+                                outcome_handle.set(Some(Arc::new(counted)));
+                            }
+                            Ok(Err(err)) => {
+                                outcome_handle.set(None);
+                                failure_handle.set(Some(Failure::from_error(&err)));
+                            }
+                            Err(err) => {
+                                outcome_handle.set(None);
+                                failure_handle.set(Some(Failure {
+                                    summary: "The detection stopped unexpectedly".to_owned(),
+                                    details: err.to_string(),
+                                }));
+                            }
+                        }
+                        task_handle.set(None);
+                    });
+                    task.set(Some((id, cancelled)));
+                },
+                "Do it!"
+            }
+        }
+    };
+
+    let result = outcome.get().as_ref().map(|counted| {
+        let (frames, max, mean) = (counted.frames.len(), counted.max(), format!("{:.2}", counted.mean()));
+        let output = counted.output.display().to_string();
+        let points = counts_polyline(&counted.counts(), 600., 150.);
+        let csv = roi::to_csv(counted_regions.get(), counted);
+        let summaries = region_summaries(counted_regions.get(), counted);
+        let stem = counted.output.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+        // Recomputed on every change of the parameters, tracking is fast next to the detection
+        let tracks = tracking.then(|| track::track(&counted.frames, track_params.get()).map(|tracks| {
+            (tracks.unique(), tracks.to_csv(&counted.frames))
+        }));
+        let crossings = match line_points.get()[..] {
+            [start, end] => Some(count_crossings(counted, track_params.get(), &CountingLine::new(start, end), *bucket.get())),
+            _ => None,
+        };
+
+        rsx! {
+            div {
+                class: "flex flex-col items-center mt-5 mb-5",
+                p {
+                    class: "text-center",
+                    "{frames} frames, at most {max} cars at once and {mean} on average"
+                }
+                svg {
+                    class: "w-full mt-2 bg-neutral-200 dark:bg-titlebar rounded-md",
+                    view_box: "0 0 600 150",
+                    preserve_aspect_ratio: "none",
+                    polyline {
+                        points: "{points}",
+                        fill: "none",
+                        stroke: "currentColor",
+                        stroke_width: "2",
+                    }
+                }
+                summaries.into_iter().map(|(region_name, region_max, region_mean)| {
+                    let region_mean = format!("{region_mean:.2}");
+                    rsx! {
+                        p { class: "text-center text-sm", "{region_name}: at most {region_max} cars at once and {region_mean} on average" }
+                    }
+                })
+                p {
+                    class: "text-sm mt-2 select-text",
+                    "The annotated video is in {output}"
+                }
+                SaveCsv { label: "Save counts as CSV", name: format!("{stem}.csv"), csv: csv, saved: saved, failure: failure }
+                tracks.map(|tracks| match tracks {
+                    Ok((unique, tracks_csv)) => rsx! {
+                        p { class: "text-center mt-5", "{unique} different cars went through the video" }
+                        SaveCsv { label: "Save tracks as CSV", name: format!("{stem}_tracks.csv"), csv: tracks_csv, saved: saved, failure: failure }
+                    },
+                    Err(err) => rsx! {
+                        p { class: "text-sm text-red-500 mt-5", "{err}" }
+                    },
+                })
+                crossings.map(|crossings| match crossings {
+                    Ok((cars_in, cars_out, crossings_csv)) => rsx! {
+                        p { class: "text-center mt-5", "{cars_in} cars crossed the line in and {cars_out} out" }
+                        SaveCsv { label: "Save crossings as CSV", name: format!("{stem}_crossings.csv"), csv: crossings_csv, saved: saved, failure: failure }
+                    },
+                    Err(err) => rsx! {
+                        p { class: "text-sm text-red-500 mt-5", "{err}" }
+                    },
+                })
+                p {
+                    class: "text-sm mt-1 select-text",
+                    "{saved}"
+                }
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "Video"
+                }
+                div {
+                    class: "w-4/5",
+                    PathPicker { placeholder: placeholder_video, valid: valid_video, filter: "video", extensions: video::EXTENSIONS }
+                    select {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                        value: "{method}",
+                        onchange: move |evt| method.set(evt.value.clone()),
+                        detect::registry(*backend.get()).into_iter().map(|detector| {
+                            let (id, name) = (detector.id(), detector.name());
+                            rsx! { option { key: "{id}", value: "{id}", "{name}" } }
+                        })
+                    }
+                    pair.then(|| rsx! {
+                        p {
+                            class: "text-sm mt-2",
+                            "Image of the empty scene, leave it empty to compare with the first frame"
+                        }
+                        PathPicker { placeholder: placeholder_reference, valid: valid_reference, filter: "image", extensions: IMAGE_EXTENSIONS }
+                    })
+                    BackendSelect { backend: backend }
+                    MethodControls { method: method.get(), haar: haar_params, diff: diff_params, library: library }
+                    TrackControls { enabled: tracking, params: track_params }
+                    button {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+                        onclick: move |_| {
+                            let video = match valid_video.get() {
+                                Some(video) if video.is_file() => video.clone(),
+                                _ => {
+                                    failure.set(Some(Failure::new("Pick an existing video")));
+                                    return;
+                                }
+                            };
+                            let backend = *backend.get();
+                            let (frame_handle, loading_handle, failure_handle) = (frame.clone(), loading_frame.clone(), failure.clone());
+                            loading_frame.set(true);
+
+                            cx.spawn(async move {
+                                let result = tokio::task::spawn_blocking(move || {
+                                    let png = video::first_frame(&video, backend)?;
+                                    Ok::<_, DetectError>(frame_of(&png)?)
+                                }).await;
+
+                                match result {
+                                    Ok(Ok(loaded)) => frame_handle.set(Some(loaded)),
+                                    Ok(Err(err)) => failure_handle.set(Some(Failure::from_error(&err))),
+                                    Err(err) => failure_handle.set(Some(Failure {
+                                        summary: "Could not read the first frame".to_owned(),
+                                        details: err.to_string(),
+                                    })),
+                                }
+                                loading_handle.set(false);
+                            });
+                        },
+                        loading_frame.then(|| rsx! { Spinner {} })
+                        "Draw a counting line or regions on the first frame"
+                    }
+                    frame.get().as_ref().map(|frame| rsx! {
+                        LinePicker { frame: frame, points: line_points, bucket: bucket }
+                        RegionEditor { frame: frame, regions: regions, failure: failure }
+                    })
+                    div {
+                        class: "flex justify-center items-center",
+                        action
+                    }
+                    result
+                }
+            }
+        }
+    })
+}
+
+/// Cars that crossed the line in and out, and the crossings of each time bucket as CSV.
+fn count_crossings(counted: &VideoOutcome, params: &TrackerParams, counting_line: &CountingLine, seconds: f64) -> Result<(usize, usize, String)> {
+    let tracks = track::track(&counted.frames, params)?;
+    let crossings = line::crossings(&counted.frames, &tracks, counting_line)?;
+    let buckets = line::buckets(&crossings, counted.frames.len(), counted.fps, seconds)?;
+    let (cars_in, cars_out) = line::totals(&buckets);
+    Ok((cars_in, cars_out, line::to_csv(&buckets)))
+}
+
+/// Most and average cars of each include region per frame.
+fn region_summaries(regions: &[Region], counted: &VideoOutcome) -> Vec<(String, usize, f64)> {
+    let frames: Vec<Vec<RegionCount>> = counted.frames.iter().map(|detections| roi::counts(regions, detections)).collect();
+    roi::includes(regions).enumerate().map(|(i, region)| {
+        let counts: Vec<usize> = frames.iter().map(|frame| frame[i].count).collect();
+        let max = counts.iter().copied().max().unwrap_or(0);
+        let mean = counts.iter().sum::<usize>() as f64 / counts.len().max(1) as f64;
+        (region.name.clone(), max, mean)
+    }).collect()
+}
+
+/// Data URL of an image encoded as `ext`, taken as PNG if the extension isn't known.
+fn data_url(ext: &str, contents: &[u8]) -> String {
+    let format = export::Format::from_extension(ext).unwrap_or(export::Format::Png);
+    format!("data:{};base64,{}", format.mime(), base64::encode(contents))
+}
+
+/// Image to draw on, as PNG in base64, and its size in pixels.
+type Frame = (String, u32, u32);
+
+/// Decodes an image in any supported format into a [`Frame`].
+fn frame_of(contents: &[u8]) -> Result<Frame> {
+    let img = image::load_from_memory(contents)?;
+    let mut png = std::io::Cursor::new(vec![]);
+    img.write_to(&mut png, image::ImageOutputFormat::Png)?;
+    Ok((base64::encode(png.into_inner()), img.width(), img.height()))
+}
+
+/// Width frames are shown at while drawing on them, in CSS pixels.
+const DRAWING_WIDTH: f64 = 640.;
+
+/// Pixels of the frame per CSS pixel of the drawing.
+fn drawing_scale((_, width, _): &Frame) -> f64 {
+    *width as f64 / DRAWING_WIDTH
+}
+
+/// Shows the frame with the SVG `children` on top, both the children and the clicks are in
+/// pixels of the frame.
+#[inline_props]
+fn DrawingSurface<'a>(cx: Scope, frame: &'a Frame, onclick: EventHandler<'a, (f32, f32)>, children: Element<'a>) -> Element {
+    let (image, width, height) = frame;
+    let scale = drawing_scale(frame);
+
+    cx.render(rsx! {
+        div {
+            class: "relative mt-1",
+            style: "width: {DRAWING_WIDTH}px;",
+            img {
+                class: "block cursor-crosshair",
+                style: "width: {DRAWING_WIDTH}px;",
+                src: "data:image/png;base64,{image}",
+                onclick: move |evt| {
+                    let click = evt.element_coordinates();
+                    onclick.call(((click.x * scale) as f32, (click.y * scale) as f32));
+                },
+            }
+            svg {
+                class: "absolute top-0 left-0 w-full h-full pointer-events-none",
+                view_box: "0 0 {width} {height}",
+                children
+            }
+        }
+    })
+}
+
+/// Two clicks on the frame set the ends of the counting line.
+#[inline_props]
+fn LinePicker<'a>(cx: Scope, frame: &'a Frame, points: &'a UseState<Vec<(f32, f32)>>, bucket: &'a UseState<f64>) -> Element {
+    let stroke = (2. * drawing_scale(frame)).max(1.);
+    let hint = match points.len() {
+        0 => "Click where the counting line starts",
+        1 => "Click where the counting line ends",
+        _ => "Cars crossing to the right of the line, going from its start to its end, count as in",
+    };
+    let segment = match points.get()[..] {
+        [(x1, y1), (x2, y2)] => Some(rsx! {
+            line { x1: "{x1}", y1: "{y1}", x2: "{x2}", y2: "{y2}", stroke: "red", stroke_width: "{stroke}" }
+        }),
+        _ => None,
+    };
+
+    cx.render(rsx! {
+        p { class: "text-sm mt-2", "{hint}" }
+        DrawingSurface {
+            frame: frame,
+            onclick: move |point| points.modify(|current| match current[..] {
+                [start] => vec![start, point],
+                _ => vec![point],
+            }),
+            points.iter().map(|(x, y)| rsx! {
+                circle { cx: "{x}", cy: "{y}", r: "{stroke * 2.}", fill: "red" }
+            })
+            segment
+        }
+        div {
+            class: "flex items-end mt-2",
+            div {
+                class: "w-1/2 mr-1",
+                ParamInput {
+                    label: "Seconds per time bucket", value: *bucket.get(), step: 1.,
+                    onchange: move |v| bucket.set(v),
+                }
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-1/2 ml-1",
+                onclick: move |_| points.set(vec![]),
+                "Remove the line"
+            }
+        }
+    })
+}
+
+/// Corners of a polygon as the `points` of an SVG element.
+fn svg_points(points: &[(f32, f32)]) -> String {
+    points.iter().map(|(x, y)| format!("{x},{y}")).collect::<Vec<_>>().join(" ")
+}
+
+/// Clicks on the frame add the corners of a polygon, which is then kept as an include or an
+/// exclude region. Regions can also be loaded from and saved to JSON.
+#[inline_props]
+fn RegionEditor<'a>(cx: Scope, frame: &'a Frame, regions: &'a UseState<Vec<Region>>, failure: &'a UseState<Option<Failure>>) -> Element {
+    let corners: &UseState<Vec<(f32, f32)>> = use_state(&cx, Vec::new);
+    let name: &UseState<String> = use_state(&cx, || "".to_owned());
+
+    let stroke = (2. * drawing_scale(frame)).max(1.);
+    let pending = svg_points(corners.get());
+    let add = move |kind: RegionKind| {
+        let region_name = match name.trim() {
+            "" => format!("Region {}", regions.len() + 1),
+            picked => picked.to_owned(),
+        };
+        let region = Region::new(region_name, kind, corners.get().clone());
+        match region.validate() {
+            Ok(_) => {
+                regions.modify(|current| {
+                    let mut next = current.clone();
+                    next.push(region);
+                    next
+                });
+                corners.set(vec![]);
+                name.set("".to_owned());
+            }
+            Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+        }
+    };
+
+    cx.render(rsx! {
+        p {
+            class: "text-sm mt-2",
+            "Click the corners of a region, then add it. Without include regions the whole image is used"
+        }
+        DrawingSurface {
+            frame: frame,
+            onclick: move |point| corners.modify(|current| {
+                let mut next = current.clone();
+                next.push(point);
+                next
+            }),
+            regions.iter().enumerate().map(|(i, region)| {
+                let points = svg_points(&region.points);
+                let (fill, line) = match region.kind {
+                    RegionKind::Include => ("rgba(0, 255, 0, 0.25)", "lime"),
+                    RegionKind::Exclude => ("rgba(255, 0, 0, 0.25)", "red"),
+                };
+                rsx! {
+                    polygon { key: "{i}", points: "{points}", fill: "{fill}", stroke: "{line}", stroke_width: "{stroke}" }
+                }
+            })
+            (!corners.is_empty()).then(|| rsx! {
+                polyline { points: "{pending}", fill: "none", stroke: "yellow", stroke_width: "{stroke}" }
+            })
+            corners.iter().map(|(x, y)| rsx! {
+                circle { cx: "{x}", cy: "{y}", r: "{stroke * 2.}", fill: "yellow" }
+            })
+        }
+        div {
+            class: "flex mt-2",
+            input {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-1/4",
+                "type": "text",
+                placeholder: "Name",
+                value: "{name}",
+                oninput: move |evt| name.set(evt.value.clone()),
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/4",
+                onclick: move |_| add(RegionKind::Include),
+                "Add as include"
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/4",
+                onclick: move |_| add(RegionKind::Exclude),
+                "Add as exclude"
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 ml-2 w-1/4",
+                onclick: move |_| corners.set(vec![]),
+                "Clear the corners"
+            }
+        }
+        ul {
+            class: "text-sm mt-2",
+            regions.iter().enumerate().map(|(i, region)| {
+                let (region_name, kind, amount) = (&region.name, region.kind.name(), region.points.len());
+                rsx! {
+                    li {
+                        key: "{i}",
+                        class: "flex justify-between items-center mt-1",
+                        span { "{region_name}, {kind}, {amount} corners" }
+                        button {
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md px-2 py-1",
+                            onclick: move |_| regions.modify(|current| {
+                                let mut next = current.clone();
+                                next.remove(i);
+                                next
+                            }),
+                            "Remove"
+                        }
+                    }
+                }
+            })
+        }
+        div {
+            class: "flex mt-2",
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-1/2 mr-1",
+                onclick: move |_| {
+                    let path = rfd::FileDialog::new()
+                    .add_filter("regions", &["json"])
+                    .set_directory(home_dir())
+                    .pick_file();
+
+                    if let Some(path) = path {
+                        match roi::load(&path) {
+                            Ok(loaded) => regions.set(loaded),
+                            Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+                        }
+                    }
+                },
+                "Load regions"
+            }
+            button {
+                class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 w-1/2 ml-1",
+                onclick: move |_| {
+                    let path = rfd::FileDialog::new()
+                    .add_filter("regions", &["json"])
+                    .set_directory(home_dir())
+                    .set_file_name("regions.json")
+                    .save_file();
+
+                    if let Some(path) = path {
+                        if let Err(err) = roi::save(regions.get(), &path) {
+                            failure.set(Some(Failure::from_error(err.as_ref())));
+                        }
+                    }
+                },
+                "Save regions"
+            }
+        }
+    })
+}
+
+/// Button that saves `csv` where the user picks, suggesting `name` as the file name.
+#[inline_props]
+fn SaveCsv<'a>(
+    cx: Scope,
+    label: &'a str,
+    name: String,
+    csv: String,
+    saved: &'a UseState<String>,
+    failure: &'a UseState<Option<Failure>>,
+) -> Element {
+    cx.render(rsx! {
+        button {
+            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-full",
+            onclick: move |_| {
+                let path = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_directory(home_dir())
+                .set_file_name(name)
+                .save_file();
+
+                if let Some(path) = path {
+                    match std::fs::write(&path, csv) {
+                        Ok(()) => saved.set(format!("Saved to {}", path.display())),
+                        Err(err) => failure.set(Some(Failure::from_error(&err))),
+                    }
+                }
+            },
+            "{label}"
+        }
+    })
+}
+
+/// Checkbox that turns on tracking, with the parameters of the tracker once it's on.
+#[inline_props]
+fn TrackControls<'a>(cx: Scope, enabled: &'a UseState<bool>, params: &'a UseState<TrackerParams>) -> Element {
+    let current = *params.get();
+    let on = *enabled.get();
+    let error = current.validate().err().map(|err| err.to_string()).unwrap_or_default();
+
+    cx.render(rsx! {
+        label {
+            class: "flex items-center text-sm mt-2",
+            input {
+                class: "mr-2",
+                "type": "checkbox",
+                checked: "{on}",
+                oninput: move |evt| enabled.set(evt.value == "true"),
+            }
+            "Follow the cars from one frame to the next and count each car once"
+        }
+        on.then(|| rsx! {
+            div {
+                class: "grid grid-cols-4 gap-2 mt-2",
+                ParamInput {
+                    label: "Min overlap", value: current.min_iou as f64, step: 0.05,
+                    onchange: move |v: f64| params.set(TrackerParams { min_iou: v as f32, ..current }),
+                }
+                ParamInput {
+                    label: "Max distance", value: current.max_distance as f64, step: 0.1,
+                    onchange: move |v: f64| params.set(TrackerParams { max_distance: v as f32, ..current }),
+                }
+                ParamInput {
+                    label: "Max missed frames", value: current.max_missed as f64, step: 1.,
+                    onchange: move |v: f64| params.set(TrackerParams { max_missed: v.max(0.) as usize, ..current }),
+                }
+                ParamInput {
+                    label: "Min detections", value: current.min_hits as f64, step: 1.,
+                    onchange: move |v: f64| params.set(TrackerParams { min_hits: v.max(0.) as usize, ..current }),
+                }
+            }
+            (!error.is_empty()).then(|| rsx! {
+                p {
+                    class: "text-sm text-red-500 mt-2",
+                    "{error}"
+                }
+            })
+        })
+    })
+}
+
+/// Points of an SVG polyline plotting `counts` over a `width` by `height` box, higher counts on top.
+fn counts_polyline(counts: &[usize], width: f64, height: f64) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let step = width / counts.len().saturating_sub(1).max(1) as f64;
+    counts.iter()
+        .enumerate()
+        .map(|(i, &count)| format!("{:.1},{:.1}", i as f64 * step, height - count as f64 / max * height))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lists the environment checks with a hint to fix each failed one.
+fn DoctorPage(cx: Scope) -> Element {
+    let checks: &UseState<Vec<Check>> = use_state(&cx, doctor::run);
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "Doctor"
+                }
+                div {
+                    class: "w-4/5",
+                    table {
+                        class: "table-auto w-full",
+                        tbody {
+                            checks.iter().map(|check| {
+                                let (name, hint) = (check.name, check.hint);
+                                let (mark, found) = match &check.status {
+                                    Ok(found) => ("✓", found.clone()),
+                                    Err(err) => ("✗", err.clone()),
+                                };
+                                rsx! {
+                                    tr {
+                                        key: "{name}",
+                                        class: "border-t border-neutral-300 dark:border-neutral-700 align-top",
+                                        td { class: "p-2", "{mark}" }
+                                        td { class: "p-2 whitespace-nowrap", "{name}" }
+                                        td {
+                                            class: "p-2 select-text",
+                                            p { "{found}" }
+                                            (!check.passed()).then(|| rsx! {
+                                                p { class: "text-sm text-red-500 mt-1", "{hint}" }
+                                            })
+                                        }
+                                    }
+                                }
+                            })
+                        }
+                    }
+                    button {
+                        class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-5 w-full",
+                        onclick: move |_| checks.set(doctor::run()),
+                        "Check again"
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// A past run and its thumbnail as a data URL, empty if it couldn't be read.
+type Entry = (history::Run, String);
+
+/// Data URL of a file of the folder of a run, empty if it couldn't be read.
+fn run_image(dir: &Path, file: &str) -> String {
+    let ext = Path::new(file).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    std::fs::read(dir.join(file))
+        .map(|contents| data_url(ext, &contents))
+        .unwrap_or_default()
+}
+
+fn load_history() -> Result<Vec<Entry>> {
+    let entries = history::list()?.into_iter().map(|run| {
+        // Videos without a thumbnail have nothing to show
+        let file = run.thumbnail.clone().or_else(|| run.video.is_none().then(|| run.output.clone()));
+        let thumbnail = run.dir().ok().zip(file)
+            .map(|(dir, file)| run_image(&dir, &file))
+            .unwrap_or_default();
+        (run, thumbnail)
+    }).collect();
+    Ok(entries)
+}
+
+/// Name of a method of the [`detect::registry`], its id if it isn't known.
+fn method_name(id: &str) -> String {
+    detect::find(id, Backend::default())
+        .map(|detector| detector.name().to_owned())
+        .unwrap_or_else(|| id.to_owned())
+}
+
+/// Lists the past runs, newest first, and reopens the one clicked.
+fn HistoryPage(cx: Scope) -> Element {
+    let failure: &UseState<Option<Failure>> = use_state(&cx, || None);
+    let entries: &UseState<Vec<Entry>> = use_state(&cx, || load_history().unwrap_or_default());
+    let selected: &UseState<Option<history::Run>> = use_state(&cx, || None);
+
+    let content = if let Some(run) = selected.get() {
+        rsx! {
+            RunDetails {
+                run: run,
+                onclose: move |_| selected.set(None),
+                onremove: move |_| {
+                    if let Some(run) = selected.get() {
+                        if let Err(err) = history::remove(run) {
+                            failure.set(Some(Failure::from_error(err.as_ref())));
+                            return;
+                        }
+                    }
+                    selected.set(None);
+                    match load_history() {
+                        Ok(loaded) => entries.set(loaded),
+                        Err(err) => failure.set(Some(Failure::from_error(err.as_ref()))),
+                    }
+                },
+            }
+        }
+    } else if entries.is_empty() {
+        rsx! {
+            p { class: "text-center", "Nothing here yet, the results of every detection are kept here." }
+        }
+    } else {
+        rsx! {
+            div {
+                class: "grid grid-cols-3 gap-4",
+                entries.iter().map(|(run, thumbnail)| {
+                    let (id, date, method, count) = (&run.id, run.date(), method_name(&run.method), run.count());
+                    rsx! {
+                        button {
+                            key: "{id}",
+                            class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 flex flex-col items-center",
+                            onclick: move |_| selected.set(Some(run.clone())),
+                            img {
+                                class: "w-full h-32 object-contain",
+                                src: "{thumbnail}"
+                            }
+                            p { class: "text-sm mt-2", "{method}, {count} cars" }
+                            p { class: "text-xs", "{date}" }
+                        }
+                    }
+                })
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        Main {
+            footer: false,
+            failure: failure,
+            div {
+                class: "flex flex-col items-center justify-center",
+                h1 {
+                    class: "font-sans font-thin mb-5 text-xl",
+                    "History"
+                }
+                div {
+                    class: "w-4/5 mb-5",
+                    content
+                }
+            }
+        }
+    })
+}
+
+/// Everything kept of one run.
+#[inline_props]
+fn RunDetails<'a>(cx: Scope, run: &'a history::Run, onclose: EventHandler<'a>, onremove: EventHandler<'a>) -> Element {
+    let dir = run.dir().unwrap_or_default();
+    // Runs on a video show the first annotated frame
+    let shown = match &run.video {
+        Some(_) => run.thumbnail.as_ref(),
+        None => Some(&run.output),
+    };
+    let image = shown.map(|file| run_image(&dir, file)).unwrap_or_default();
+    let (method, date, count) = (method_name(&run.method), run.date(), run.count());
+    let summary = match &run.video {
+        Some(video) => format!("At most {count} cars at once in {}", video.source.display()),
+        None => format!("There were {count} cars in the image!"),
+    };
+    let backend = Backend::from_id(&run.backend).map(|backend| backend.name()).unwrap_or(run.backend.as_str());
+    let params = serde_json::to_string_pretty(&run.params).unwrap_or_default();
+    let folder = dir.display().to_string();
+
+    cx.render(rsx! {
+        div {
+            class: "flex flex-col items-center",
+            p {
+                class: "text-center",
+                "{summary}"
+            }
+            img {
+                class: "mt-2 w-2/3",
+                src: "{image}"
+            }
+            table {
+                class: "table-auto w-full mt-5",
+                tbody {
+                    tr {
+                        class: "border-t border-neutral-300 dark:border-neutral-700",
+                        td { class: "p-2", "Method" }
+                        td { class: "p-2", "{method} on {backend}" }
+                    }
+                    tr {
+                        class: "border-t border-neutral-300 dark:border-neutral-700",
+                        td { class: "p-2", "Date" }
+                        td { class: "p-2", "{date} UTC" }
+                    }
+                    tr {
+                        class: "border-t border-neutral-300 dark:border-neutral-700 align-top",
+                        td { class: "p-2", "Parameters" }
+                        td { pre { class: "p-2 text-xs select-text", "{params}" } }
+                    }
+                    tr {
+                        class: "border-t border-neutral-300 dark:border-neutral-700",
+                        td { class: "p-2", "Folder" }
+                        td { class: "p-2 select-text", "{folder}" }
+                    }
+                }
+            }
+            div {
+                class: "flex w-full",
+                button {
+                    class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-1/2 mr-1",
+                    onclick: move |_| onclose.call(()),
+                    "Back"
+                }
+                button {
+                    class: "bg-neutral-200 dark:bg-titlebar text-dark dark:text-white rounded-md p-2 mt-2 w-1/2 ml-1",
+                    onclick: move |_| onremove.call(()),
+                    "Delete"
+                }
+            }
+        }
+    })
+}
+
+/// Points to the Doctor page when a check failed at startup.
+#[inline_props]
+fn StartupNotice<'a>(cx: Scope, checks: &'a [Check]) -> Element {
+    let dismissed: &UseState<bool> = use_state(&cx, || false);
+    if doctor::healthy(checks) || *dismissed.get() {
+        return None;
+    }
+    let failed = checks.iter().filter(|check| !check.passed()).map(|check| check.name).collect::<Vec<_>>().join(", ");
+
+    cx.render(rsx! {
+        div {
+            style: "z-index: 20;",
+            class: "fixed bottom-4 left-4 w-1/3 bg-amber-500 text-white rounded-md p-3 shadow-lg",
+            div {
+                class: "flex items-start justify-between",
+                p { class: "text-sm", "Some checks failed: {failed}" }
+                button {
+                    class: "ml-2 hover:text-gray-200",
+                    onclick: move |_| dismissed.set(true),
+                    "✕"
+                }
+            }
+            Link {
+                class: "text-xs underline hover:text-gray-200",
+                to: "/doctor",
+                "See how to fix it"
+            }
+        }
+    })
+}
+
+fn app(cx: Scope) -> Element {
+    // Run once at startup so a missing module is reported before the first detection
+    let checks: &UseState<Vec<Check>> = use_state(&cx, doctor::run);
+
+    cx.render(rsx!(
+        Router {
+            Route { to: "/", DiffMethod {} }
+            Route { to: "/haar", HaarMethod {} }
+            Route { to: "/background", BackgroundPage {} }
+            Route { to: "/batch", BatchPage {} }
+            Route { to: "/video", VideoPage {} }
+            Route { to: "/history", HistoryPage {} }
+            Route { to: "/doctor", DoctorPage {} }
+            StartupNotice { checks: checks.get() }
+        }
+    ))
+}